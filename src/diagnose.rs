@@ -0,0 +1,172 @@
+use crate::highlight::{self, Highlight};
+use crate::matching;
+use crate::{ProgError, RegexFlags};
+use colored::Color;
+use log::info;
+use regex_syntax::ast::{self, Ast};
+use std::ops::Range;
+
+/// The longest prefix of a pattern that still matches somewhere in the haystack.
+pub struct NearMiss {
+    /// Number of top-level elements (concatenation pieces) in the pattern.
+    pub elements: usize,
+    /// Number of elements in the longest matching prefix.
+    pub matched: usize,
+    /// Span of the matching prefix in the pattern.
+    pub prefix: Range<usize>,
+    /// Span in the pattern of the first element that couldn't be matched.
+    pub failed: Option<Range<usize>>,
+    /// Leftmost match of the prefix in the haystack, if even the first element matched.
+    pub found: Option<Range<usize>>,
+}
+
+impl NearMiss {
+    /// One-line description of where matching stopped, for reports.
+    pub fn summary(&self, pattern: &str) -> String {
+        match &self.failed {
+            Some(failed) if self.matched == 0 => format!(
+                "not even the first pattern element `{}` matches",
+                &pattern[failed.clone()]
+            ),
+            Some(failed) => format!(
+                "the longest matching prefix `{}` ({} of {} pattern elements) \
+                 fails to continue with `{}`",
+                &pattern[self.prefix.clone()],
+                self.matched,
+                self.elements,
+                &pattern[failed.clone()]
+            ),
+            None => "the whole pattern matches as a prefix".to_owned(),
+        }
+    }
+}
+
+/// Finds out how far the pattern gets before failing, by matching ever longer prefixes
+/// made of its top-level concatenation pieces.
+pub fn near_miss(
+    pattern: &str,
+    flags: &RegexFlags,
+    haystack: &[u8],
+) -> Result<NearMiss, ProgError> {
+    let elements = top_level_elements(pattern, flags.ignore_whitespace)?;
+    let mut miss = NearMiss {
+        elements: elements.len(),
+        matched: 0,
+        prefix: 0..0,
+        failed: elements.first().cloned(),
+        found: None,
+    };
+
+    // A prefix can only match if every shorter prefix does, so stop at the first failure.
+    for (i, element) in elements.iter().enumerate() {
+        let prefix = 0..element.end;
+        let re = flags.build(&pattern[prefix.clone()])?;
+        let Some(found) = re.find(haystack) else {
+            break;
+        };
+        miss.matched = i + 1;
+        miss.prefix = prefix;
+        miss.failed = elements.get(i + 1).cloned();
+        miss.found = Some(found.range());
+    }
+    Ok(miss)
+}
+
+fn top_level_elements(
+    pattern: &str,
+    ignore_whitespace: bool,
+) -> Result<Vec<Range<usize>>, ProgError> {
+    let ast = ast::parse::ParserBuilder::new()
+        .ignore_whitespace(ignore_whitespace)
+        .build()
+        .parse(pattern)
+        .map_err(|err| ProgError::ParseFailure("failed to parse regex".to_owned(), err.into()))?;
+    let span = |ast: &Ast| ast.span().start.offset..ast.span().end.offset;
+    let Ast::Concat(concat) = &ast else {
+        return Ok(vec![span(&ast)]);
+    };
+
+    // Every literal character is an element of its own in the AST, which makes for a
+    // meaningless near miss like "39 of 41 elements, failed at `A`". Adjacent literal word
+    // characters are kept together instead, so elements are words, punctuation and the
+    // other constructs of the pattern.
+    let mut elements: Vec<Range<usize>> = Vec::new();
+    let mut in_word = false;
+    for ast in &concat.asts {
+        let word =
+            matches!(ast, Ast::Literal(literal) if literal.c.is_alphanumeric() || literal.c == '_');
+        match elements.last_mut() {
+            Some(last) if word && in_word => last.end = span(ast).end,
+            _ => elements.push(span(ast)),
+        }
+        in_word = word;
+    }
+    Ok(elements)
+}
+
+/// Prints where matching stopped, both in the pattern and in the haystack.
+pub fn print(pattern: &str, haystack: &[u8], miss: &NearMiss, name: &str) {
+    let mut highlights = vec![Highlight {
+        span: miss.prefix.clone(),
+        color: Color::Green.into(),
+        label: Some("matched".to_owned()),
+    }];
+    if let Some(failed) = &miss.failed {
+        highlights.push(Highlight {
+            span: failed.clone(),
+            color: Color::Red.into(),
+            label: Some("failed".to_owned()),
+        });
+    }
+    info!(
+        "Longest matching prefix ({} of {} pattern elements):",
+        miss.matched, miss.elements
+    );
+    println!("{}", highlight::render_pattern(pattern, &highlights));
+
+    match &miss.found {
+        None => info!("Not even the first pattern element matches anywhere in {name}"),
+        Some(found) => {
+            let (line, col) = matching::line_col(haystack, found.end);
+            info!("Matching stopped at line {line}, column {col} of {name}:");
+
+            let start = matching::line_start(haystack, found.start);
+            let end = matching::line_end(haystack, found.end);
+            let mut highlights = vec![Highlight {
+                span: found.clone(),
+                color: Color::Green.into(),
+                label: Some("matched".to_owned()),
+            }];
+            if found.end < end {
+                // Color the whole character the next element failed on.
+                let next = haystack[found.end + 1..]
+                    .iter()
+                    .position(|&b| b & 0xC0 != 0x80)
+                    .map_or(haystack.len(), |i| found.end + 1 + i);
+                highlights.push(Highlight {
+                    span: found.end..next,
+                    color: Color::Red.into(),
+                    label: Some("failed".to_owned()),
+                });
+            }
+            println!("{}", highlight::render(haystack, start..end, &highlights));
+            // Without colors, the marks already show where matching stopped (and shift the
+            // text, so a caret counted on the raw line would be off).
+            if highlight::coloring() {
+                let stop_line = matching::line_start(haystack, found.end);
+                let width = highlight::display_bytes(&haystack[stop_line..found.end])
+                    .chars()
+                    .count();
+                println!("{}^", " ".repeat(width));
+            }
+        }
+    }
+
+    if let Some(failed) = &miss.failed {
+        info!(
+            "Failed pattern element at column {}: `{}`",
+            failed.start + 1,
+            &pattern[failed.clone()]
+        );
+    }
+}
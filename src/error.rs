@@ -0,0 +1,62 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io;
+
+/// Everything that can make regex404 fail, each with its own exit code:
+///
+/// | Code | Meaning |
+/// |------|---------|
+/// | 0 | Success |
+/// | 1 | No match (`NoMatch`) |
+/// | 2 | Invalid command line arguments (reported by clap) |
+/// | 3 | A regex or glob failed to parse (`ParseFailure`) |
+/// | 4 | Reading a file or stdin failed (`IO`) |
+/// | 5 | The renovate config couldn't be parsed (`Config`) |
+/// | 6 | A check failed, e.g. a custom manager that matches nothing (`Validation`) |
+/// | 7 | The file arguments match no files (`NoFiles`) |
+#[derive(Debug)]
+pub enum ProgError {
+    IO(String, io::Error),
+    ParseFailure(String, Box<dyn Error + Send + Sync>),
+    Config(String, serde_json::Error),
+    Validation(String),
+    NoMatch,
+    NoFiles,
+}
+
+impl ProgError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ProgError::NoMatch => 1,
+            ProgError::ParseFailure(..) => 3,
+            ProgError::IO(..) => 4,
+            ProgError::Config(..) => 5,
+            ProgError::Validation(_) => 6,
+            ProgError::NoFiles => 7,
+        }
+    }
+}
+
+impl Display for ProgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgError::IO(msg, _)
+            | ProgError::ParseFailure(msg, _)
+            | ProgError::Config(msg, _)
+            | ProgError::Validation(msg) => f.write_str(msg),
+            ProgError::NoMatch => f.write_str("found no matches"),
+            ProgError::NoFiles => f.write_str("no files to check the pattern against"),
+        }
+    }
+}
+
+impl Error for ProgError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProgError::IO(_, err) => Some(err),
+            ProgError::ParseFailure(_, err) => Some(err.as_ref()),
+            ProgError::Config(_, err) => Some(err),
+            ProgError::Validation(_) | ProgError::NoMatch | ProgError::NoFiles => None,
+        }
+    }
+}
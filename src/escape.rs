@@ -0,0 +1,43 @@
+use regex::Regex;
+
+/// Escapes `text` so that it matches literally, spelling out newlines and tabs so the
+/// pattern fits on one line.
+pub fn escape(text: &str) -> String {
+    regex::escape(text)
+        .replace('\n', r"\n")
+        .replace('\r', r"\r")
+        .replace('\t', r"\t")
+}
+
+/// Like `escape`, but replaces the first version-like token with a `currentValue` group and
+/// the first package-like token (`org/name`, `registry/image`) with a `depName` group.
+pub fn skeleton(text: &str) -> String {
+    let token = Regex::new(r#"[^\s:=@,"'`]+"#).expect("token regex should be valid");
+    let version =
+        Regex::new(r"^v?\d+(\.\d+)+([-+][\w.-]+)?$").expect("version regex should be valid");
+    let package = Regex::new(r"^[\w.-]+(/[\w.-]+)+$").expect("package regex should be valid");
+
+    let mut out = String::new();
+    let mut last = 0;
+    let (mut has_version, mut has_package) = (false, false);
+    for m in token.find_iter(text) {
+        out.push_str(&escape(&text[last..m.start()]));
+        if !has_version && version.is_match(m.as_str()) {
+            has_version = true;
+            out.push_str(r"(?<currentValue>[\w.+-]+)");
+        } else if !has_package && package.is_match(m.as_str()) {
+            has_package = true;
+            out.push_str(r"(?<depName>[\w./-]+)");
+        } else {
+            out.push_str(&escape(m.as_str()));
+        }
+        last = m.end();
+    }
+    out.push_str(&escape(&text[last..]));
+    out
+}
+
+/// The pattern as it must be written inside a JSON string, e.g. in `matchStrings`.
+pub fn json_string(pattern: &str) -> String {
+    serde_json::to_string(pattern).expect("a string should always serialize")
+}
@@ -0,0 +1,348 @@
+use crate::palette::PaletteColor;
+use clap::ValueEnum;
+use log::debug;
+use regex_syntax::ast::{self, Ast, GroupKind};
+use std::convert::Infallible;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+static MARKS: OnceLock<Marks> = OnceLock::new();
+
+/// A region of the haystack (byte offsets) to be colored.
+pub struct Highlight {
+    pub span: Range<usize>,
+    pub color: PaletteColor,
+    /// What the region is, shown when it's marked without colors.
+    pub label: Option<String>,
+}
+
+/// How highlights are shown when colors are disabled.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Marks {
+    /// Brackets with a label, e.g. `[currentValue:v1.12.1]`
+    #[default]
+    Brackets,
+    /// Carets with a label on a line below the text
+    Carets,
+}
+
+/// Sets how highlights are marked without colors.
+pub fn init(marks: Marks) {
+    MARKS.set(marks).expect("marks should only be set once");
+}
+
+fn marks() -> Marks {
+    MARKS.get().copied().unwrap_or_default()
+}
+
+pub fn coloring() -> bool {
+    let coloring = colored::control::SHOULD_COLORIZE.should_colorize();
+    if !coloring {
+        debug!("Disabling coloring as the environment doesn't seem to handle it.");
+    }
+    coloring
+}
+
+/// Renders `text[range]` with every highlight applied to exactly the bytes it covers.
+///
+/// Highlights may nest or overlap: each byte takes the color of the shortest highlight
+/// covering it, so inner capture groups stay visible inside outer ones. When two
+/// highlights cover the exact same span the last one wins.
+/// Bytes that aren't valid UTF-8 are shown as `\xNN` escapes.
+///
+/// Without colors, highlights are marked with brackets or carets instead (see `init`).
+pub fn render(text: &[u8], range: Range<usize>, highlights: &[Highlight]) -> String {
+    if coloring() {
+        return render_with(text, range, highlights, |segment, color| match color {
+            Some(color) => color.paint(segment),
+            None => segment.to_owned(),
+        });
+    }
+    match marks() {
+        Marks::Brackets => brackets(text, range, highlights),
+        Marks::Carets => carets(text, range, highlights),
+    }
+}
+
+/// Like `render`, for regex source such as a pattern or skeleton. Brackets would change
+/// what the pattern says (and it may be copied from the output), so without colors it's
+/// only marked with carets, if those are configured, and shown as is otherwise.
+pub fn render_pattern(pattern: &str, highlights: &[Highlight]) -> String {
+    if !coloring() && marks() == Marks::Brackets {
+        return pattern.to_owned();
+    }
+    render(pattern.as_bytes(), 0..pattern.len(), highlights)
+}
+
+/// Like `render`, but lets `paint` turn every segment and its color (if any) into output,
+/// e.g. HTML instead of terminal escapes.
+pub fn render_with(
+    text: &[u8],
+    range: Range<usize>,
+    highlights: &[Highlight],
+    paint: impl Fn(&str, Option<PaletteColor>) -> String,
+) -> String {
+    let mut out = String::new();
+    for seg in bounds(&range, highlights).windows(2) {
+        let (start, end) = (seg[0], seg[1]);
+        let segment = display_bytes(&text[start..end]);
+        let color = highlights
+            .iter()
+            .rev()
+            .filter(|h| h.span.start <= start && end <= h.span.end)
+            .min_by_key(|h| h.span.len())
+            .map(|h| h.color);
+        out.push_str(&paint(&segment, color));
+    }
+    out
+}
+
+/// Every offset in `range` where a highlight starts or ends, along with the ends of `range`.
+fn bounds(range: &Range<usize>, highlights: &[Highlight]) -> Vec<usize> {
+    let mut bounds: Vec<usize> = vec![range.start, range.end];
+    // Empty spans color nothing, and in bytes mode they may point inside a code point.
+    for h in highlights.iter().filter(|h| !h.span.is_empty()) {
+        for pos in [h.span.start, h.span.end] {
+            if range.contains(&pos) {
+                bounds.push(pos);
+            }
+        }
+    }
+    bounds.sort_unstable();
+    bounds.dedup();
+    bounds
+}
+
+/// Wraps every highlight in brackets with its label, e.g. `[currentValue:v1.12.1]`.
+/// Nested highlights get nested brackets.
+fn brackets(text: &[u8], range: Range<usize>, highlights: &[Highlight]) -> String {
+    // Clipped to the range, and ordered so that outer highlights open first.
+    let mut marks: Vec<(Range<usize>, Option<&str>)> = highlights
+        .iter()
+        .map(|h| {
+            let start = h.span.start.max(range.start);
+            let end = h.span.end.min(range.end).max(start);
+            (start..end, h.label.as_deref())
+        })
+        .filter(|(span, _)| !span.is_empty())
+        .collect();
+    marks.sort_by_key(|(span, _)| (span.start, std::cmp::Reverse(span.end)));
+
+    let bounds = bounds(&range, highlights);
+    let mut out = String::new();
+    for (i, &pos) in bounds.iter().enumerate() {
+        // Inner highlights close first
+        for (span, _) in marks.iter().rev() {
+            if span.end == pos {
+                out.push(']');
+            }
+        }
+        let Some(&next) = bounds.get(i + 1) else {
+            break;
+        };
+        for (span, label) in &marks {
+            if span.start == pos {
+                out.push('[');
+                if let Some(label) = label {
+                    out.push_str(label);
+                    out.push(':');
+                }
+            }
+        }
+        out.push_str(&display_bytes(&text[pos..next]));
+    }
+    out
+}
+
+/// Shows every line of `text[range]`, each followed by a line of carets under every
+/// highlight on it, with its label.
+fn carets(text: &[u8], range: Range<usize>, highlights: &[Highlight]) -> String {
+    let mut sorted: Vec<&Highlight> = highlights.iter().filter(|h| !h.span.is_empty()).collect();
+    sorted.sort_by_key(|h| h.span.start);
+
+    let mut lines = Vec::new();
+    let mut line_start = range.start;
+    loop {
+        let line_end = text[line_start..range.end]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(range.end, |i| line_start + i);
+        let line = display_bytes(&text[line_start..line_end]);
+        lines.push(line.trim_end_matches('\r').to_owned());
+
+        for h in &sorted {
+            let start = h.span.start.max(line_start);
+            let end = h.span.end.min(line_end);
+            if start >= end {
+                continue;
+            }
+            // Tabs are kept, so the carets line up with the text above
+            let mut underline: String = display_bytes(&text[line_start..start])
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let width = display_bytes(&text[start..end])
+                .trim_end_matches('\r')
+                .chars()
+                .count();
+            underline.push_str(&"^".repeat(width.max(1)));
+            if let Some(label) = &h.label {
+                underline.push(' ');
+                underline.push_str(label);
+            }
+            lines.push(underline);
+        }
+
+        if line_end == range.end {
+            break;
+        }
+        line_start = line_end + 1;
+    }
+    lines.join("\n")
+}
+
+/// Lossy display of `bytes`: valid UTF-8 is kept as is, anything else is escaped as `\xNN`.
+pub fn display_bytes(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        out.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            out.push_str(&format!("\\x{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// A capture group as it appears in the pattern source.
+pub struct PatternGroup {
+    /// Capture index, as used by `Captures::get`.
+    pub index: usize,
+    pub name: Option<String>,
+    /// Byte offsets of the whole group in the pattern, including its parentheses.
+    pub span: Range<usize>,
+}
+
+/// Finds the span of every capture group (named or not) in `pattern`.
+///
+/// Uses the `regex-syntax` AST rather than scanning for parentheses, so escaped parens,
+/// character classes and group names appearing as literal text are all handled.
+/// `ignore_whitespace` must match the `x` flag the pattern was compiled with.
+pub fn pattern_groups(pattern: &str, ignore_whitespace: bool) -> Vec<PatternGroup> {
+    let mut parser = ast::parse::ParserBuilder::new()
+        .ignore_whitespace(ignore_whitespace)
+        .build();
+    match parser.parse(pattern) {
+        Ok(ast) => ast::visit(&ast, GroupCollector(Vec::new())).unwrap_or_default(),
+        Err(err) => {
+            debug!("Failed to parse pattern for highlighting: {err}");
+            Vec::new()
+        }
+    }
+}
+
+struct GroupCollector(Vec<PatternGroup>);
+
+impl ast::Visitor for GroupCollector {
+    type Output = Vec<PatternGroup>;
+    type Err = Infallible;
+
+    fn finish(self) -> Result<Self::Output, Self::Err> {
+        Ok(self.0)
+    }
+
+    fn visit_pre(&mut self, ast: &Ast) -> Result<(), Self::Err> {
+        if let Ast::Group(group) = ast {
+            let (index, name) = match &group.kind {
+                GroupKind::CaptureIndex(index) => (*index, None),
+                GroupKind::CaptureName { name, .. } => (name.index, Some(name.name.clone())),
+                GroupKind::NonCapturing(_) => return Ok(()),
+            };
+            self.0.push(PatternGroup {
+                index: index as usize,
+                name,
+                span: group.span.start.offset..group.span.end.offset,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hl(span: Range<usize>, color: u8, label: &str) -> Highlight {
+        Highlight {
+            span,
+            color: PaletteColor::Ansi256(color),
+            label: Some(label.to_owned()),
+        }
+    }
+
+    /// Renders with `{color:text}` instead of terminal escapes.
+    fn tagged(text: &[u8], range: Range<usize>, highlights: &[Highlight]) -> String {
+        render_with(text, range, highlights, |segment, color| match color {
+            Some(PaletteColor::Ansi256(color)) => format!("{{{color}:{segment}}}"),
+            Some(color) => panic!("unexpected color {color:?}"),
+            None => segment.to_owned(),
+        })
+    }
+
+    #[test]
+    fn nested_groups_keep_the_inner_color() {
+        let highlights = [hl(0..6, 1, "outer"), hl(2..4, 2, "inner")];
+        assert_eq!(tagged(b"abcdef", 0..6, &highlights), "{1:ab}{2:cd}{1:ef}");
+        assert_eq!(
+            brackets(b"abcdef", 0..6, &highlights),
+            "[outer:ab[inner:cd]ef]"
+        );
+    }
+
+    #[test]
+    fn nested_groups_sharing_a_bound() {
+        let highlights = [hl(0..4, 1, "outer"), hl(0..2, 2, "inner")];
+        assert_eq!(tagged(b"abcd", 0..4, &highlights), "{2:ab}{1:cd}");
+        assert_eq!(brackets(b"abcd", 0..4, &highlights), "[outer:[inner:ab]cd]");
+    }
+
+    #[test]
+    fn identical_spans_last_one_wins() {
+        let highlights = [hl(1..3, 1, "first"), hl(1..3, 2, "second")];
+        assert_eq!(tagged(b"abcd", 0..4, &highlights), "a{2:bc}d");
+    }
+
+    #[test]
+    fn empty_spans_are_ignored() {
+        let highlights = [hl(2..2, 1, "empty")];
+        assert_eq!(tagged(b"abcd", 0..4, &highlights), "abcd");
+        assert_eq!(brackets(b"abcd", 0..4, &highlights), "abcd");
+        assert_eq!(carets(b"abcd", 0..4, &highlights), "abcd");
+    }
+
+    #[test]
+    fn spans_crossing_the_range_are_clipped() {
+        let highlights = [hl(0..4, 1, "x"), hl(5..8, 2, "y")];
+        assert_eq!(tagged(b"abcdefgh", 2..6, &highlights), "{1:cd}e{2:f}");
+        assert_eq!(brackets(b"abcdefgh", 2..6, &highlights), "[x:cd]e[y:f]");
+        assert_eq!(carets(b"abcdefgh", 2..6, &highlights), "cdef\n^^ x\n   ^ y");
+    }
+
+    #[test]
+    fn invalid_utf8_is_escaped() {
+        let highlights = [hl(1..2, 1, "g")];
+        assert_eq!(tagged(b"a\xffb", 0..3, &highlights), "a{1:\\xFF}b");
+        assert_eq!(brackets(b"a\xffb", 0..3, &highlights), "a[g:\\xFF]b");
+    }
+
+    #[test]
+    fn carets_underline_every_line_of_a_span() {
+        let highlights = [hl(1..4, 1, "g")];
+        assert_eq!(carets(b"ab\ncd", 0..5, &highlights), "ab\n ^ g\ncd\n^ g");
+    }
+
+    #[test]
+    fn carets_keep_tabs_and_drop_carriage_returns() {
+        let highlights = [hl(1..2, 1, "g")];
+        assert_eq!(carets(b"\tb\r\nc", 0..5, &highlights), "\tb\n\t^ g\nc");
+    }
+}
@@ -0,0 +1,185 @@
+use crate::ProgError;
+use globset::GlobBuilder;
+use log::{debug, warn};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+/// Stdin can only be read once, but every pattern is run against it.
+static STDIN: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Where the haystack is read from.
+pub enum Input {
+    File(PathBuf),
+    Stdin,
+    Text(String),
+}
+
+impl Input {
+    /// Reads the raw haystack. UTF-16 input (detected by its byte order mark) is
+    /// transcoded to UTF-8, anything else is returned as is.
+    pub fn read(&self) -> Result<Vec<u8>, ProgError> {
+        let haystack = match self {
+            Input::File(file) => fs::read(file)
+                .map_err(|err| ProgError::IO(format!("failed to read file {file:?}"), err))?,
+            Input::Stdin => match STDIN.get() {
+                Some(haystack) => haystack.clone(),
+                None => {
+                    let mut haystack = Vec::new();
+                    std::io::stdin()
+                        .read_to_end(&mut haystack)
+                        .map_err(|err| ProgError::IO("failed to read stdin".to_owned(), err))?;
+                    STDIN.get_or_init(|| haystack).clone()
+                }
+            },
+            Input::Text(text) => return Ok(text.as_bytes().to_owned()),
+        };
+
+        let haystack = match haystack.as_slice() {
+            [0xFF, 0xFE, rest @ ..] => {
+                debug!("Transcoding UTF-16LE input {}", self.name());
+                decode_utf16(rest, u16::from_le_bytes)
+            }
+            [0xFE, 0xFF, rest @ ..] => {
+                debug!("Transcoding UTF-16BE input {}", self.name());
+                decode_utf16(rest, u16::from_be_bytes)
+            }
+            _ => haystack,
+        };
+        if std::str::from_utf8(&haystack).is_err() {
+            debug!("{} is not valid UTF-8", self.name());
+        }
+        Ok(haystack)
+    }
+
+    /// Name used for the input in grep-style `name:line:col:` output.
+    pub fn plain_name(&self) -> String {
+        match self {
+            Input::File(file) => file.display().to_string(),
+            _ => self.name(),
+        }
+    }
+
+    /// Name used for the input in the output.
+    pub fn name(&self) -> String {
+        match self {
+            Input::File(file) => format!("{file:?}"),
+            Input::Stdin => "<stdin>".to_owned(),
+            Input::Text(_) => "<text>".to_owned(),
+        }
+    }
+}
+
+/// Transcodes UTF-16 to UTF-8. Unpaired surrogates and an odd trailing byte become U+FFFD.
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Vec<u8> {
+    let pairs = bytes.chunks_exact(2);
+    let odd = !pairs.remainder().is_empty();
+    let mut text: String = char::decode_utf16(pairs.map(|pair| from_bytes([pair[0], pair[1]])))
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    if odd {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    text.into_bytes()
+}
+
+/// Expands file arguments into inputs: `-` is stdin, directories are walked recursively and
+/// anything else containing glob characters (`*`, `?`, `[`, `{`) is matched against the file
+/// tree, unless a file by that name exists.
+pub fn expand(specs: &[String]) -> Result<Vec<Input>, ProgError> {
+    let mut inputs = Vec::new();
+    for spec in specs {
+        let path = Path::new(spec);
+        if spec == "-" {
+            inputs.push(Input::Stdin);
+        } else if path.is_dir() {
+            inputs.extend(walk_files(path).map(Input::File));
+        } else if !path.is_file() && spec.contains(['*', '?', '[', '{']) {
+            let found = glob(spec)?;
+            if found.is_empty() {
+                warn!("No files matched {spec:?}");
+            }
+            inputs.extend(found.into_iter().map(Input::File));
+        } else {
+            inputs.push(Input::File(path.to_owned()));
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err(ProgError::NoFiles);
+    }
+    Ok(inputs)
+}
+
+fn walk_files(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+}
+
+fn glob(spec: &str) -> Result<Vec<PathBuf>, ProgError> {
+    let matcher = GlobBuilder::new(spec)
+        .literal_separator(true)
+        .build()
+        .map_err(|err| {
+            ProgError::ParseFailure(format!("failed to parse glob {spec:?}"), err.into())
+        })?
+        .compile_matcher();
+
+    // Only walk the part of the tree that can match: everything up to the first glob character.
+    let base: PathBuf = Path::new(spec)
+        .components()
+        .take_while(|c| {
+            !c.as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '[', '{'])
+        })
+        .collect();
+    let root = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base.as_path()
+    };
+    debug!("Walking {root:?} for glob {spec:?}");
+
+    Ok(walk_files(root)
+        .filter_map(|file| {
+            // Remove leading ./ when walking the current directory, as it isn't in the glob.
+            let file = match file.strip_prefix("./") {
+                Ok(stripped) if !spec.starts_with("./") => stripped.to_owned(),
+                _ => file,
+            };
+            matcher.is_match(&file).then_some(file)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_both_byte_orders() {
+        let le = decode_utf16(b"a\0\xe9\0=\xd8\x00\xde", u16::from_le_bytes);
+        let be = decode_utf16(b"\0a\0\xe9\xd8=\xde\x00", u16::from_be_bytes);
+        assert_eq!(String::from_utf8(le).unwrap(), "a\u{e9}\u{1f600}");
+        assert_eq!(String::from_utf8(be).unwrap(), "a\u{e9}\u{1f600}");
+    }
+
+    #[test]
+    fn odd_trailing_byte_is_replaced() {
+        let decoded = decode_utf16(b"a\0b", u16::from_le_bytes);
+        assert_eq!(String::from_utf8(decoded).unwrap(), "a\u{fffd}");
+    }
+
+    #[test]
+    fn unpaired_surrogate_is_replaced() {
+        let decoded = decode_utf16(b"=\xd8a\0", u16::from_le_bytes);
+        assert_eq!(String::from_utf8(decoded).unwrap(), "\u{fffd}a");
+    }
+}
@@ -0,0 +1,132 @@
+use crate::RegexFlags;
+use crate::input::Input;
+use crate::matching::{self, Cap, Match};
+use serde::Serialize;
+use std::ops::Range;
+
+/// Identifies the format of `--output json`. Bump the version on breaking changes.
+pub const SCHEMA: &str = "regex404/match";
+pub const VERSION: u32 = 1;
+
+/// Top-level document of `--output json`.
+#[derive(Serialize)]
+pub struct Report {
+    pub schema: &'static str,
+    pub version: u32,
+    pub results: Vec<MatchResult>,
+}
+
+impl Report {
+    pub fn new(results: Vec<MatchResult>) -> Self {
+        Report {
+            schema: SCHEMA,
+            version: VERSION,
+            results,
+        }
+    }
+}
+
+/// All matches of one pattern in one input.
+#[derive(Serialize)]
+pub struct MatchResult {
+    pub pattern: String,
+    /// Inline flags the pattern was compiled with, e.g. `ix`.
+    pub flags: String,
+    /// Whether the pattern was compiled in bytes mode (Unicode disabled).
+    pub bytes: bool,
+    pub input: String,
+    pub matches: Vec<JsonMatch>,
+}
+
+impl MatchResult {
+    pub fn new(
+        pattern: &str,
+        flags: &RegexFlags,
+        input: &Input,
+        haystack: &[u8],
+        matches: &[Match],
+    ) -> Self {
+        MatchResult {
+            pattern: pattern.to_owned(),
+            flags: flags.inline(),
+            bytes: flags.bytes,
+            input: input.plain_name(),
+            matches: matches
+                .iter()
+                .map(|m| JsonMatch::new(haystack, m))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct JsonMatch {
+    pub text: String,
+    pub span: Span,
+    pub captures: Vec<Capture>,
+}
+
+impl JsonMatch {
+    pub fn new(haystack: &[u8], m: &Match) -> Self {
+        JsonMatch {
+            text: crate::highlight::display_bytes(&haystack[m.span.clone()]),
+            span: Span::new(haystack, &m.span),
+            captures: m
+                .caps
+                .iter()
+                .map(|cap| Capture::new(haystack, cap))
+                .collect(),
+        }
+    }
+}
+
+/// A named or positional capture group of a match.
+#[derive(Serialize)]
+pub struct Capture {
+    pub index: usize,
+    /// `null` for positional groups.
+    pub name: Option<String>,
+    /// One of `matched`, `empty` and `not_participating`.
+    pub state: &'static str,
+    /// `null` when the group didn't participate in the match.
+    pub value: Option<String>,
+    pub span: Option<Span>,
+}
+
+impl Capture {
+    fn new(haystack: &[u8], cap: &Cap) -> Self {
+        Capture {
+            index: cap.index,
+            name: cap.name.clone(),
+            state: cap.state.as_str(),
+            value: cap.span.as_ref().map(|_| cap.value.clone()),
+            span: cap.span.as_ref().map(|span| Span::new(haystack, span)),
+        }
+    }
+}
+
+/// Byte offsets, plus 1-based lines and byte columns, of a region of the input.
+#[derive(Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    pub fn new(haystack: &[u8], span: &Range<usize>) -> Self {
+        let (start_line, start_column) = matching::line_col(haystack, span.start);
+        let (end_line, end_column) = matching::line_col(haystack, span.end);
+        Span {
+            start: span.start,
+            end: span.end,
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        }
+    }
+}
@@ -1,34 +1,319 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
 use log::{debug, info, warn};
-use regex::Regex;
-use serde::Deserialize;
-use std::fmt::Debug;
+use regex::bytes::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use std::error::Error;
 use std::fs::{self};
-use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+mod diagnose;
+mod error;
+mod escape;
+mod highlight;
+mod input;
+mod json;
+mod matching;
+mod overlay;
+mod palette;
+mod renovate;
+
+use error::ProgError;
+use highlight::{Highlight, Marks};
+use input::Input;
+use matching::{CapState, Match};
+use palette::{GroupColors, PaletteColor};
+
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  no match
+  2  invalid arguments
+  3  a regex or glob failed to parse
+  4  reading a file or stdin failed
+  5  the renovate config couldn't be parsed
+  6  a check failed (e.g. a custom manager found no matches or missed required groups)";
 
 /// Regex404 is a tool to debug regular expressions on some content in a file.
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    after_help = EXIT_CODES
+)]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
     #[command(flatten)]
     main: DefaultProgram,
+
+    /// Colors for capture groups: names (e.g. blue, bright-red), 256-color indexes (0-255)
+    /// or #rrggbb
+    #[arg(
+        long,
+        global = true,
+        value_delimiter = ',',
+        value_name = "COLORS",
+        env = "REGEX404_PALETTE"
+    )]
+    palette: Vec<PaletteColor>,
+
+    /// How to mark capture groups when colors are off (e.g. with NO_COLOR or when piped)
+    #[arg(long, global = true, value_enum, default_value_t)]
+    marks: Marks,
 }
 
 /// Default program (you can omit any (sub)command to run this program).
 #[derive(Args, Debug)]
+#[command(group(
+    ArgGroup::new("input")
+        .required(true)
+        .multiple(true)
+        .args(["file", "text", "paths"])
+))]
+#[command(group(
+    ArgGroup::new("pattern")
+        .required(true)
+        .multiple(true)
+        .args(["regex", "regex_file"])
+))]
 struct DefaultProgram {
-    /// Path to file to check the pattern against
-    #[arg(short, long, required = true)]
-    file: Option<PathBuf>,
+    /// Path to file to check the pattern against, or - to read stdin.
+    /// Can be repeated, and may be a directory or a glob
+    #[arg(short, long)]
+    file: Vec<String>,
+
+    /// Text to check the pattern against, instead of a file
+    #[arg(short, long, conflicts_with_all = ["file", "paths"])]
+    text: Option<String>,
+
+    /// More files, directories or globs to check the pattern against
+    paths: Vec<String>,
+
+    /// Regex to run on the input. Can be repeated to run several patterns
+    #[arg(short, long)]
+    regex: Vec<String>,
+
+    /// File with a regex per line, or a single pattern spanning the whole file
+    /// when --ignore-whitespace is set
+    #[arg(long, value_name = "FILE")]
+    regex_file: Vec<PathBuf>,
+
+    #[command(flatten)]
+    flags: RegexFlags,
+
+    #[command(flatten)]
+    options: MatchOptions,
+}
+
+/// How matches are looked for and reported.
+#[derive(Args, Debug, Default)]
+struct MatchOptions {
+    /// Output format
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value_t,
+        conflicts_with_all = ["diagnose", "replace", "overlay", "json_string"]
+    )]
+    output: OutputFormat,
+
+    /// When there's no match, show the longest prefix of the pattern that still matches
+    /// and where in the input it stopped matching
+    #[arg(long)]
+    diagnose: bool,
+
+    /// Run the regex on each line separately, like grep, and print file:line:col per match
+    #[arg(long)]
+    lines: bool,
+
+    /// Preview replacing every match with TEMPLATE, expanding $name, ${name} and $1 like
+    /// `Regex::replace_all`
+    #[arg(long, value_name = "TEMPLATE", conflicts_with = "lines")]
+    replace: Option<String>,
+
+    /// Show the --replace result as a unified diff against the input
+    #[arg(long, requires = "replace")]
+    diff: bool,
+
+    /// Show a single view of each input with the matches of all patterns highlighted,
+    /// flagging regions matched by more than one pattern
+    #[arg(long, conflicts_with_all = ["lines", "replace", "diagnose"])]
+    overlay: bool,
+
+    /// After a successful run, print the regex as a JSON string, as it must appear in
+    /// renovate.json's customManagers[].matchStrings
+    #[arg(long)]
+    json_string: bool,
+
+    /// Print a color legend of the capture groups, set when running several patterns.
+    #[arg(skip)]
+    legend: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum OutputFormat {
+    /// Colored, human readable output
+    #[default]
+    Text,
+    /// A single JSON document with every match and capture (schema "regex404/match", version 1)
+    Json,
+}
+
+impl MatchOptions {
+    fn find_matches(&self, re: &Regex, haystack: &[u8]) -> Vec<Match> {
+        if self.lines {
+            matching::find_line_matches(re, haystack)
+        } else {
+            matching::find_matches(re, haystack)
+        }
+    }
+}
 
-    /// Regex to run on {file}
-    #[arg(short, long, required = true)]
-    regex: Option<Regex>,
+/// Options passed on to `RegexBuilder` when compiling a pattern.
+#[derive(Args, Debug, Default)]
+struct RegexFlags {
+    /// Match letters case-insensitively (like `(?i)`)
+    #[arg(short = 'i', long)]
+    case_insensitive: bool,
+
+    /// Let ^ and $ match at the start and end of every line (like `(?m)`)
+    #[arg(short = 'm', long)]
+    multi_line: bool,
+
+    /// Let . match \n as well (like `(?s)`)
+    #[arg(short = 's', long)]
+    dot_matches_new_line: bool,
+
+    /// Make quantifiers lazy by default, and `?`-suffixed ones greedy (like `(?U)`)
+    #[arg(short = 'U', long)]
+    swap_greed: bool,
+
+    /// Verbose mode: ignore whitespace and allow # comments in the pattern (like `(?x)`)
+    #[arg(short = 'x', long)]
+    ignore_whitespace: bool,
+
+    /// Bytes mode: disable Unicode so that . and classes match any byte and \xNN matches raw
+    /// bytes, for input that isn't UTF-8. Invalid UTF-8 is displayed as \xNN escapes
+    #[arg(short = 'b', long)]
+    bytes: bool,
+
+    /// Approximate size limit, in bytes, of the compiled regex
+    #[arg(long, value_name = "BYTES")]
+    size_limit: Option<usize>,
+
+    /// Approximate size limit, in bytes, of the cache used by the lazy DFA
+    #[arg(long, value_name = "BYTES")]
+    dfa_size_limit: Option<usize>,
+}
+
+impl DefaultProgram {
+    /// All patterns given with --regex and --regex-file, in that order.
+    fn patterns(&self) -> Result<Vec<String>, ProgError> {
+        let mut patterns = self.regex.clone();
+        for file in &self.regex_file {
+            let content = fs::read_to_string(file)
+                .map_err(|err| ProgError::IO(format!("failed to read regex file {file:?}"), err))?;
+            if self.flags.ignore_whitespace {
+                patterns.push(content);
+            } else {
+                patterns.extend(
+                    content
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(str::to_owned),
+                );
+            }
+        }
+        Ok(patterns)
+    }
+}
+
+impl RegexFlags {
+    fn build(&self, pattern: &str) -> Result<Regex, ProgError> {
+        let mut builder = RegexBuilder::new(pattern);
+        builder
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .swap_greed(self.swap_greed)
+            .ignore_whitespace(self.ignore_whitespace)
+            .unicode(!self.bytes);
+        if let Some(limit) = self.size_limit {
+            builder.size_limit(limit);
+        }
+        if let Some(limit) = self.dfa_size_limit {
+            builder.dfa_size_limit(limit);
+        }
+        builder
+            .build()
+            .map_err(|err| ProgError::ParseFailure("failed to parse regex".to_owned(), err.into()))
+    }
+
+    /// Like `build`, but compiles all patterns into one `RegexSet`.
+    fn build_set<'a>(
+        &self,
+        patterns: impl IntoIterator<Item = &'a str>,
+    ) -> Result<RegexSet, ProgError> {
+        let mut builder = RegexSetBuilder::new(patterns);
+        builder
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .swap_greed(self.swap_greed)
+            .ignore_whitespace(self.ignore_whitespace)
+            .unicode(!self.bytes);
+        if let Some(limit) = self.size_limit {
+            builder.size_limit(limit);
+        }
+        if let Some(limit) = self.dfa_size_limit {
+            builder.dfa_size_limit(limit);
+        }
+        builder.build().map_err(|err| {
+            ProgError::ParseFailure("failed to parse regex set".to_owned(), err.into())
+        })
+    }
+
+    /// Flags in inline-flag notation, e.g. `ix`.
+    fn inline(&self) -> String {
+        let mut inline = String::new();
+        for (set, flag) in [
+            (self.case_insensitive, 'i'),
+            (self.multi_line, 'm'),
+            (self.dot_matches_new_line, 's'),
+            (self.swap_greed, 'U'),
+            (self.ignore_whitespace, 'x'),
+        ] {
+            if set {
+                inline.push(flag);
+            }
+        }
+        inline
+    }
+
+    /// Effective flags in inline-flag notation (e.g. `ix`) and any non-default limits.
+    fn describe(&self) -> String {
+        let inline = self.inline();
+
+        let mut parts: Vec<String> = Vec::new();
+        if !inline.is_empty() {
+            parts.push(format!("flags: {inline}"));
+        }
+        if self.bytes {
+            parts.push("bytes mode".to_owned());
+        }
+        if let Some(limit) = self.size_limit {
+            parts.push(format!("size-limit: {limit}"));
+        }
+        if let Some(limit) = self.dfa_size_limit {
+            parts.push(format!("dfa-size-limit: {limit}"));
+        }
+        parts.join(", ")
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -41,260 +326,446 @@ enum Commands {
         /// Path to renovate-formatted file
         #[arg(short, long, default_value = "renovate.json")]
         file: PathBuf,
-    },
-}
 
-enum ProgError {
-    IO(String),
-    ParseFailure(String),
-    NoMatch,
-}
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t)]
+        output: renovate::OutputFormat,
+    },
 
-/// Capture group contents
-struct Cap {
-    name: String,
-    value: String,
+    /// Turn a literal snippet (e.g. a line copied from a Dockerfile) into a regex,
+    /// a skeleton with suggested capture groups and a JSON string for matchStrings.
+    Escape {
+        /// Text to escape, read from stdin when omitted
+        text: Option<String>,
+    },
 }
 
-fn main() -> Result<(), ProgError> {
+fn main() -> ExitCode {
     env_logger::builder()
         .filter_level(log::LevelFilter::Info)
         .parse_env("RUST_LOG")
         .init();
     let cli = Cli::parse();
-    match cli.command.unwrap_or(Commands::Main(cli.main)) {
-        Commands::Renovate { file } => renovate(&file),
-        Commands::Main(args) => {
-            let file = args.file.unwrap();
-            let pathy = Path::new(&file);
-            match_file(pathy, args.regex.unwrap())
+    palette::init(cli.palette.clone());
+    highlight::init(cli.marks);
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            let mut source = err.source();
+            while let Some(cause) = source {
+                eprintln!("Caused by: {cause}");
+                source = cause.source();
+            }
+            ExitCode::from(err.exit_code())
         }
     }
 }
 
-fn match_file(file: &Path, re: Regex) -> Result<(), ProgError> {
-    let haystack = fs::read_to_string(file)
-        .map_err(|err| ProgError::IO(format!("failed to read file {file:?}: {err}")))?;
-
-    let coloring = colored::control::ShouldColorize::from_env().should_colorize();
-    if !coloring {
-        debug!("Disabling coloring as the environment doesn't seem to handle it.");
+fn run(cli: Cli) -> Result<(), ProgError> {
+    match cli.command.unwrap_or(Commands::Main(cli.main)) {
+        Commands::Renovate { file, output } => renovate::run(&file, output),
+        Commands::Escape { text } => escape_snippet(text),
+        Commands::Main(args) => default_program(args),
     }
+}
 
-    debug!("Parsed regex: {re}");
+fn default_program(args: DefaultProgram) -> Result<(), ProgError> {
+    let patterns = args
+        .patterns()?
+        .iter()
+        .map(|pattern| args.flags.build(pattern))
+        .collect::<Result<Vec<Regex>, ProgError>>()?;
+    let inputs = match args.text {
+        Some(text) => vec![Input::Text(text)],
+        None => input::expand(&[args.file, args.paths].concat())?,
+    };
 
-    if re.capture_names().len() <= 1 {
-        warn!("no cap group");
-        return Ok(());
+    let mut options = args.options;
+    options.legend = patterns.len() > 1;
+    if options.output == OutputFormat::Json {
+        return print_json(&patterns, &inputs, &args.flags, &options);
+    }
+    if options.overlay {
+        overlay::run(&patterns, &inputs, &args.flags)?;
+    } else {
+        match_patterns(&patterns, &inputs, &args.flags, &options)?;
     }
 
-    for name in re.capture_names() {
-        match name {
-            Some(name) => {
-                debug!("Looking for capture group '{name}'");
-            }
-            None => continue,
+    if options.json_string {
+        for re in &patterns {
+            print_json_string(re, &args.flags)?;
         }
     }
+    Ok(())
+}
 
-    let captures = match re.captures(&haystack) {
-        None => return Err(ProgError::NoMatch),
-        Some(cap) => cap,
-    };
-
-    let matcha = captures.get_match().as_str();
-    debug!("Found match: {matcha}");
-
-    let mut caps: Vec<Cap> = Vec::new();
-
-    for name in re.capture_names() {
-        match name {
-            Some(name) => match captures.name(name) {
-                Some(val) => {
-                    let valstr = val.as_str();
-                    let cap = Cap {
-                        name: name.to_owned(),
-                        value: valstr.to_owned(),
-                    };
-                    debug!("Found match: <{name}>={valstr}");
-                    caps.push(cap);
+/// Prints the matches of every pattern in every input as one JSON document.
+fn print_json(
+    patterns: &[Regex],
+    inputs: &[Input],
+    flags: &RegexFlags,
+    options: &MatchOptions,
+) -> Result<(), ProgError> {
+    let mut results = Vec::new();
+    for re in patterns {
+        for input in inputs {
+            let haystack = match input.read() {
+                Ok(haystack) => haystack,
+                Err(err) if inputs.len() > 1 => {
+                    warn!("{err}");
+                    continue;
                 }
-                None => warn!("Capture group <{name}> missing value."),
-            },
-            None => continue,
+                Err(err) => return Err(err),
+            };
+            let matches = options.find_matches(re, &haystack);
+            results.push(json::MatchResult::new(
+                re.as_str(),
+                flags,
+                input,
+                &haystack,
+                &matches,
+            ));
         }
     }
 
-    let colors: Vec<colored::Color> = vec![
-        colored::Color::Blue,
-        colored::Color::Green,
-        colored::Color::Red,
-        colored::Color::Black,
-    ];
-
-    let mut regexstring = re.to_string();
-    let mut regexstringprint = regexstring.to_owned();
-    let mut matchstring = matcha.to_owned();
-    let mut matches: Vec<String> = Vec::new();
-
-    for (i, cap) in caps.into_iter().enumerate() {
-        let color = colors[i % colors.len()];
-        let Cap { name, value: val } = cap;
-        let namecolor = name.color(color);
-        let valcolor = val.color(color);
-
-        let found = format!("<{namecolor}>: {valcolor}");
-        debug!("{found}");
-
-        if coloring {
-            // Find the capture group name and expand coloring to the wrapping parentheses
-            let mut regexstring_copy = regexstring.to_owned();
-            let capgroup_name = format!("<{name}>");
-            let capgroup_start = regexstring_copy.find(&capgroup_name);
-            let mut capgroupstringfind = regexstring_copy.to_owned();
-            capgroupstringfind
-                .split_off(capgroup_start.expect("capgroup should have start"))
-                .truncate(0);
-            let capgroup_start2 = capgroupstringfind.rfind("(");
-            let mut end: Option<usize> = None;
-            let mut opened_parens = 1;
-            for i in capgroup_start2.expect("capture group to have a start match") + 1
-                ..regexstring_copy.len()
-            {
-                let c = regexstring_copy.chars().nth(i).expect("char should exist");
-
-                // If we find other groups within this group, or the match includes parentheses,
-                // make sure we keep searching for the end.
-                if c == '(' {
-                    opened_parens += 1;
-                }
-                if c == ')' {
-                    opened_parens -= 1;
-                    end = Some(i + 1); // include the wrapping )
-                }
-                if opened_parens == 0 {
-                    break;
-                }
-            }
-            let mut capg = regexstring_copy.split_off(capgroup_start2.unwrap());
-            let capg_end = capg.split_off(end.unwrap() - regexstring_copy.len());
-            regexstringprint = regexstring_copy + &capg.color(color).to_string() + &capg_end;
-            regexstring = regexstringprint.to_string();
-            matchstring = matchstring.replace(&val, &valcolor.to_string());
-        }
-        matches.push(found);
+    let matched = results.iter().any(|result| !result.matches.is_empty());
+    let report =
+        serde_json::to_string_pretty(&json::Report::new(results)).expect("report should serialize");
+    println!("{report}");
+
+    if !matched {
+        return Err(ProgError::NoMatch);
     }
+    Ok(())
+}
 
-    info!("Regex:");
-    println!("{regexstringprint}");
-    info!("Match:");
-    println!("{matchstring}");
-    info!("Capture groups:");
-    matches.iter().for_each(|m| println!("{m}"));
+/// Prints the pattern as it must appear in a JSON string in renovate.json
+/// (`customManagers[].matchStrings`), after checking that it decodes back to a string that
+/// compiles, with the same flags, to the same pattern.
+fn print_json_string(re: &Regex, flags: &RegexFlags) -> Result<(), ProgError> {
+    let json = escape::json_string(re.as_str());
+    let decoded: String = serde_json::from_str(&json).map_err(|err| {
+        ProgError::Validation(format!("JSON string {json} doesn't decode: {err}"))
+    })?;
+    let compiled = flags.build(&decoded)?;
+    if compiled.as_str() != re.as_str() {
+        return Err(ProgError::Validation(format!(
+            "JSON string {json} compiles to {:?} instead of the pattern",
+            compiled.as_str()
+        )));
+    }
 
+    info!("JSON string (for matchStrings):");
+    println!("{json}");
     Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-struct CustomMatcher {
-    #[serde(rename = "customType")]
-    type_: String,
-    #[serde(rename = "managerFilePatterns")]
-    file_patterns: Vec<String>,
-    #[serde(rename = "matchStrings")]
-    regexes: Vec<String>,
+fn escape_snippet(text: Option<String>) -> Result<(), ProgError> {
+    let text = match text {
+        Some(text) => text,
+        None => {
+            let stdin = Input::Stdin.read()?;
+            let stdin = String::from_utf8(stdin).map_err(|err| {
+                ProgError::IO(
+                    "stdin is not valid UTF-8".to_owned(),
+                    io::Error::new(io::ErrorKind::InvalidData, err),
+                )
+            })?;
+            // Piped snippets usually come with a trailing newline that isn't part of them.
+            stdin.strip_suffix('\n').unwrap_or(&stdin).to_owned()
+        }
+    };
+
+    let escaped = escape::escape(&text);
+    let skeleton = escape::skeleton(&text);
+    let re = RegexFlags::default().build(&skeleton)?;
+    if !re.is_match(text.as_bytes()) {
+        warn!("The suggested skeleton doesn't match the snippet, please adjust it by hand.");
+    }
+
+    info!("Escaped:");
+    println!("{escaped}");
+    info!("Skeleton:");
+    let colors = GroupColors::new(re.capture_names().skip(1));
+    let highlights: Vec<Highlight> = highlight::pattern_groups(&skeleton, false)
+        .into_iter()
+        .map(|group| Highlight {
+            span: group.span,
+            color: colors.get(group.index),
+            label: Some(matching::group_mark(group.index, group.name.as_deref())),
+        })
+        .collect();
+    println!("{}", highlight::render_pattern(&skeleton, &highlights));
+    info!("JSON string (for matchStrings):");
+    println!("{}", escape::json_string(&skeleton));
+
+    Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-struct RenovateScheme {
-    #[serde(rename = "customManagers")]
-    custom_matchers: Vec<CustomMatcher>,
+/// Runs every pattern on the inputs, one after the other.
+fn match_patterns(
+    patterns: &[Regex],
+    inputs: &[Input],
+    flags: &RegexFlags,
+    options: &MatchOptions,
+) -> Result<(), ProgError> {
+    if let [re] = patterns {
+        return match_inputs(inputs, re, flags, options);
+    }
+
+    let mut matched_patterns = 0;
+    for (n, re) in patterns.iter().enumerate() {
+        info!("Pattern {} of {}:", n + 1, patterns.len());
+        match match_inputs(inputs, re, flags, options) {
+            Ok(()) => matched_patterns += 1,
+            Err(ProgError::NoMatch) => info!("No matches for pattern {}: {re}", n + 1),
+            Err(err) => return Err(err),
+        }
+    }
+    info!("{matched_patterns} of {} patterns matched", patterns.len());
+
+    if matched_patterns == 0 {
+        return Err(ProgError::NoMatch);
+    }
+    Ok(())
 }
 
-fn renovate(file: &PathBuf) -> Result<(), ProgError> {
-    let renovate_config_file = match fs::read_to_string(file) {
-        Ok(data) => data,
-        Err(err) => return Err(ProgError::IO(err.to_string() + &format!(": {file:?}"))),
-    };
-    let renovate_config: RenovateScheme = match serde_json::from_str(&renovate_config_file) {
-        Ok(conf) => conf,
-        Err(err) => return Err(ProgError::ParseFailure(err.to_string())),
-    };
+/// Runs the pattern on every input, grouping the results per input.
+fn match_inputs(
+    inputs: &[Input],
+    re: &Regex,
+    flags: &RegexFlags,
+    options: &MatchOptions,
+) -> Result<(), ProgError> {
+    if let [input] = inputs {
+        return match_file(input, re, flags, options);
+    }
 
-    for matcher in renovate_config.custom_matchers {
-        if matcher.type_.to_lowercase() != "regex" {
+    print_pattern(re, flags, options);
+
+    let mut matched_inputs = 0;
+    let mut total_matches = 0;
+    for input in inputs {
+        let name = input.name();
+        info!("File {name}:");
+        let haystack = match input.read() {
+            Ok(haystack) => haystack,
+            Err(err) => {
+                warn!("{err}");
+                continue;
+            }
+        };
+
+        let matches = options.find_matches(re, &haystack);
+        if matches.is_empty() {
+            info!("No matches in {name}");
+            if options.diagnose {
+                diagnose(re, flags, &haystack, &name)?;
+            }
             continue;
         }
+        matched_inputs += 1;
+        total_matches += matches.len();
+        print_matches(input, &haystack, &matches, options);
+        print_count(matches.len(), &name);
+        print_replacement(input, re, &haystack, options);
+    }
 
-        for pattern in &matcher.file_patterns {
-            debug!("File pattern: {pattern}");
-            // Removing leading and trailing slashes (/)
-            let pattern = pattern.trim_matches('/');
-            debug!("File pattern trimmed: {pattern}");
-
-            let file_regex = match Regex::new(pattern) {
-                Ok(re) => re,
-                Err(err) => {
-                    return Err(ProgError::ParseFailure(format!(
-                        "Failed to parse file pattern regex: {err:?}"
-                    )));
-                }
-            };
+    info!(
+        "Summary: {matched_inputs} of {} files matched, {total_matches} {} in total",
+        inputs.len(),
+        if total_matches == 1 {
+            "match"
+        } else {
+            "matches"
+        }
+    );
 
-            debug!("File pattern parsed: {file_regex}");
+    if matched_inputs == 0 {
+        return Err(ProgError::NoMatch);
+    }
+    Ok(())
+}
 
-            for entry in WalkDir::new(".") {
-                let entry = match entry {
-                    Ok(path) => path,
-                    Err(_) => continue,
-                };
+fn match_file(
+    input: &Input,
+    re: &Regex,
+    flags: &RegexFlags,
+    options: &MatchOptions,
+) -> Result<(), ProgError> {
+    let haystack = input.read()?;
+
+    let matches = options.find_matches(re, &haystack);
+    if matches.is_empty() {
+        if options.diagnose {
+            diagnose(re, flags, &haystack, &input.name())?;
+        }
+        return Err(ProgError::NoMatch);
+    }
 
-                // Easier to work with filename
-                let mut file = entry.path().to_str().expect("dir entry should exist");
+    print_pattern(re, flags, options);
+    print_matches(input, &haystack, &matches, options);
+    print_count(matches.len(), &input.name());
+    print_replacement(input, re, &haystack, options);
 
-                if !file_regex.to_string().starts_with(".") {
-                    // Remove leading ./ from file name as it might clash with regex.
-                    file = file.trim_start_matches("./");
-                }
+    Ok(())
+}
 
-                debug!("Walking into {file:?}");
+fn diagnose(re: &Regex, flags: &RegexFlags, haystack: &[u8], name: &str) -> Result<(), ProgError> {
+    let miss = diagnose::near_miss(re.as_str(), flags, haystack)?;
+    diagnose::print(re.as_str(), haystack, &miss, name);
+    Ok(())
+}
 
-                if !file_regex.is_match(file) {
-                    debug!("Skipping dir cuz no regex mach");
-                    continue;
-                }
-                debug!("Found a match with {file_regex:?} on {file:?}");
-
-                for regex in &matcher.regexes {
-                    debug!("Running regex: {regex}");
-                    let re = match Regex::new(regex) {
-                        Ok(re) => re,
-                        Err(err) => {
-                            return Err(ProgError::ParseFailure(format!(
-                                "Failed to parse regex: {err:?}"
-                            )));
-                        }
-                    };
-
-                    match match_file(entry.path(), re) {
-                        Ok(_) => (),
-                        Err(_) => debug!("Found no match for {regex} in {file}"),
-                    }
-                }
+fn print_pattern(re: &Regex, flags: &RegexFlags, options: &MatchOptions) {
+    debug!("Parsed regex: {re}");
+    for (i, name) in re.capture_names().enumerate().skip(1) {
+        debug!(
+            "Looking for capture group {}",
+            matching::group_label(i, name)
+        );
+    }
+
+    let pattern = re.as_str();
+    let colors = GroupColors::new(re.capture_names().skip(1));
+    let highlights: Vec<Highlight> = highlight::pattern_groups(pattern, flags.ignore_whitespace)
+        .into_iter()
+        .map(|group| Highlight {
+            span: group.span,
+            color: colors.get(group.index),
+            label: Some(matching::group_mark(group.index, group.name.as_deref())),
+        })
+        .collect();
+    let regexstringprint = highlight::render_pattern(pattern, &highlights);
+
+    match flags.describe() {
+        desc if desc.is_empty() => info!("Regex:"),
+        desc => info!("Regex ({desc}):"),
+    }
+    println!("{regexstringprint}");
+
+    if options.legend && re.captures_len() > 1 {
+        let legend: Vec<String> = re
+            .capture_names()
+            .enumerate()
+            .skip(1)
+            .map(|(i, name)| colors.get(i).paint(&matching::group_label(i, name)))
+            .collect();
+        info!("Legend:");
+        println!("{}", legend.join("  "));
+    }
+}
+
+fn print_matches(input: &Input, haystack: &[u8], matches: &[Match], options: &MatchOptions) {
+    for (n, m) in matches.iter().enumerate() {
+        debug!(
+            "Found match: {}",
+            highlight::display_bytes(&haystack[m.span.clone()])
+        );
+        let (highlights, found) = match_parts(m);
+
+        if options.lines {
+            let (line, col) = matching::line_col(haystack, m.span.start);
+            let start = matching::line_start(haystack, m.span.start);
+            let end = matching::line_end(haystack, m.span.start);
+            let prefix = format!("{}:{line}:{col}:", input.plain_name());
+            // Carets go on lines of their own, which have to line up with the text
+            let text = highlight::render(haystack, start..end, &highlights)
+                .replace('\n', &format!("\n{}", " ".repeat(prefix.chars().count())));
+            println!("{prefix}{}", text.trim_end_matches('\r'));
+            if !found.is_empty() {
+                println!("    {}", found.join("  "));
             }
+            continue;
+        }
+
+        info!("Match {}:", n + 1);
+        println!(
+            "{}",
+            highlight::render(haystack, m.span.clone(), &highlights)
+        );
+        if !found.is_empty() {
+            info!("Capture groups:");
+            found.iter().for_each(|f| println!("{f}"));
         }
     }
+}
 
-    Ok(())
+/// Highlights for a match, along with a `label: value` line per capture group.
+///
+/// A pattern without capture groups gets the whole match highlighted instead.
+fn match_parts(m: &Match) -> (Vec<Highlight>, Vec<String>) {
+    let colors = GroupColors::new(m.caps.iter().map(|cap| cap.name.as_deref()));
+    let mut highlights: Vec<Highlight> = Vec::new();
+    if m.caps.is_empty() {
+        highlights.push(Highlight {
+            span: m.span.clone(),
+            color: colors.get(0),
+            label: None,
+        });
+    }
+
+    let mut found: Vec<String> = Vec::new();
+    for cap in &m.caps {
+        let color = colors.get(cap.index);
+        let label = cap.label();
+        debug!("Capture group {label} is {}", cap.state.as_str());
+        if let Some(span) = &cap.span {
+            highlights.push(Highlight {
+                span: span.clone(),
+                color,
+                label: Some(matching::group_mark(cap.index, cap.name.as_deref())),
+            });
+        }
+
+        let labelcolor = color.paint(&label);
+        found.push(match cap.state {
+            CapState::Matched => format!("{labelcolor}: {}", color.paint(&cap.value)),
+            CapState::Empty => format!("{labelcolor}: \"\" (matched empty)"),
+            CapState::NotParticipating => format!("{labelcolor}: (did not participate)"),
+        });
+    }
+    (highlights, found)
 }
 
-impl Debug for ProgError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let msg = match self {
-            ProgError::IO(m) => m.to_owned(),
-            ProgError::NoMatch => "found no matches".to_owned(),
-            ProgError::ParseFailure(msg) => format!("failed to parse: {msg}").to_owned(),
-        };
-        f.write_str(&msg)
+/// Prints the haystack with every match replaced by the `--replace` template.
+fn print_replacement(input: &Input, re: &Regex, haystack: &[u8], options: &MatchOptions) {
+    let Some(template) = &options.replace else {
+        return;
+    };
+    let replaced = re.replace_all(haystack, template.as_bytes());
+    let old = highlight::display_bytes(haystack);
+    let new = highlight::display_bytes(&replaced);
+
+    if !options.diff {
+        info!("Replaced:");
+        print!("{new}");
+        if !new.ends_with('\n') {
+            println!();
+        }
+        return;
+    }
+
+    info!("Diff:");
+    let name = input.plain_name();
+    let diff = similar::TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .header(&name, &name)
+        .to_string();
+    for line in diff.lines() {
+        match line.chars().next() {
+            Some('+') if !line.starts_with("+++") => println!("{}", line.green()),
+            Some('-') if !line.starts_with("---") => println!("{}", line.red()),
+            Some('@') => println!("{}", line.cyan()),
+            _ => println!("{line}"),
+        }
     }
 }
+
+fn print_count(count: usize, name: &str) {
+    info!(
+        "Found {count} {} in {name}",
+        if count == 1 { "match" } else { "matches" }
+    );
+}
@@ -0,0 +1,188 @@
+use crate::highlight::display_bytes;
+use regex::bytes::Regex;
+use std::ops::Range;
+
+/// A single match of the pattern in the haystack.
+pub struct Match {
+    /// Byte offsets of the whole match in the haystack.
+    pub span: Range<usize>,
+    /// Every capture group of the pattern (except the implicit group 0), in index order.
+    pub caps: Vec<Cap>,
+}
+
+/// Capture group contents
+pub struct Cap {
+    /// Capture index, as used in `$1`.
+    pub index: usize,
+    pub name: Option<String>,
+    /// Captured text, with bytes that aren't valid UTF-8 escaped.
+    pub value: String,
+    /// Byte offsets in the haystack, unless the group didn't participate.
+    pub span: Option<Range<usize>>,
+    pub state: CapState,
+}
+
+/// How a capture group took part in a match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapState {
+    /// The group captured some text.
+    Matched,
+    /// The group took part in the match but captured the empty string.
+    Empty,
+    /// The group is optional (or in an untaken alternative) and didn't take part.
+    NotParticipating,
+}
+
+impl CapState {
+    fn new(capture: Option<regex::bytes::Match>) -> Self {
+        match capture {
+            Some(m) if m.is_empty() => CapState::Empty,
+            Some(_) => CapState::Matched,
+            None => CapState::NotParticipating,
+        }
+    }
+
+    /// Stable identifier, used in machine-readable output.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CapState::Matched => "matched",
+            CapState::Empty => "empty",
+            CapState::NotParticipating => "not_participating",
+        }
+    }
+}
+
+impl Cap {
+    /// `<name>` for named groups, `$index` for positional ones.
+    pub fn label(&self) -> String {
+        group_label(self.index, self.name.as_deref())
+    }
+}
+
+/// `name` for named groups, `$index` for positional ones, as used to mark groups without colors.
+pub fn group_mark(index: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) => name.to_owned(),
+        None => format!("${index}"),
+    }
+}
+
+pub fn group_label(index: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("<{name}>"),
+        None => format!("${index}"),
+    }
+}
+
+/// Runs `re` over the whole haystack and collects every match with all of its groups.
+pub fn find_matches(re: &Regex, haystack: &[u8]) -> Vec<Match> {
+    re.captures_iter(haystack)
+        .map(|captures| {
+            let caps = re
+                .capture_names()
+                .enumerate()
+                .skip(1)
+                .map(|(i, name)| {
+                    let capture = captures.get(i);
+                    Cap {
+                        index: i,
+                        name: name.map(str::to_owned),
+                        value: capture
+                            .map(|val| display_bytes(val.as_bytes()))
+                            .unwrap_or_default(),
+                        span: capture.map(|val| val.range()),
+                        state: CapState::new(capture),
+                    }
+                })
+                .collect();
+            Match {
+                span: captures.get_match().range(),
+                caps,
+            }
+        })
+        .collect()
+}
+
+/// Like `find_matches`, but runs `re` on every line separately (grep style), so matches
+/// never span lines. Spans still point into the whole haystack.
+pub fn find_line_matches(re: &Regex, haystack: &[u8]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let mut start = 0;
+    for line in haystack.split(|&b| b == b'\n') {
+        if start == haystack.len() && start > 0 {
+            // Nothing after the final newline
+            break;
+        }
+        let text = line.strip_suffix(b"\r").unwrap_or(line);
+        matches.extend(find_matches(re, text).into_iter().map(|m| m.offset(start)));
+        start += line.len() + 1;
+    }
+    matches
+}
+
+impl Match {
+    /// Moves all spans `by` bytes forward, e.g. for a match in a part of the haystack.
+    pub fn offset(mut self, by: usize) -> Self {
+        self.span = self.span.start + by..self.span.end + by;
+        for cap in &mut self.caps {
+            cap.span = cap.span.take().map(|span| span.start + by..span.end + by);
+        }
+        self
+    }
+}
+
+/// 1-based line and byte column of `offset` in `haystack`.
+pub fn line_col(haystack: &[u8], offset: usize) -> (usize, usize) {
+    let before = &haystack[..offset];
+    let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+    (line, offset - line_start(haystack, offset) + 1)
+}
+
+/// Offset of the start of the line containing `offset`.
+pub fn line_start(haystack: &[u8], offset: usize) -> usize {
+    haystack[..offset]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1)
+}
+
+/// Offset of the end of the line containing `offset`, excluding the newline.
+pub fn line_end(haystack: &[u8], offset: usize) -> usize {
+    haystack[offset..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(haystack.len(), |i| offset + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(start, end)` of every match.
+    fn spans(matches: &[Match]) -> Vec<(usize, usize)> {
+        matches.iter().map(|m| (m.span.start, m.span.end)).collect()
+    }
+
+    #[test]
+    fn crlf_line_endings_are_stripped() {
+        let re = Regex::new(r"^(\w+)$").unwrap();
+        let matches = find_line_matches(&re, b"ab\r\ncd\r\n");
+        assert_eq!(spans(&matches), [(0, 2), (4, 6)]);
+        assert_eq!(matches[1].caps[0].span, Some(4..6));
+        assert_eq!(matches[1].caps[0].value, "cd");
+    }
+
+    #[test]
+    fn no_empty_line_after_the_final_newline() {
+        let re = Regex::new("^$").unwrap();
+        assert_eq!(spans(&find_line_matches(&re, b"a\n\nb\n")), [(2, 2)]);
+        assert_eq!(spans(&find_line_matches(&re, b"a\n\nb")), [(2, 2)]);
+        assert_eq!(spans(&find_line_matches(&re, b"")), [(0, 0)]);
+    }
+
+    #[test]
+    fn last_line_without_newline_is_searched() {
+        let re = Regex::new("b+").unwrap();
+        assert_eq!(spans(&find_line_matches(&re, b"ab\nbb")), [(1, 2), (3, 5)]);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::highlight::{self, Highlight};
+use crate::input::Input;
+use crate::palette::{self, PaletteColor};
+use crate::{ProgError, RegexFlags, matching};
+use colored::Color;
+use log::{info, warn};
+use regex::bytes::Regex;
+use std::ops::Range;
+
+/// Color marking regions matched by more than one pattern.
+const OVERLAP_COLOR: PaletteColor = PaletteColor::Named(Color::Magenta);
+
+/// Shows one combined view per input, with the matches of every pattern highlighted in
+/// that pattern's color and regions matched by several patterns flagged.
+pub fn run(patterns: &[Regex], inputs: &[Input], flags: &RegexFlags) -> Result<(), ProgError> {
+    let set = flags.build_set(patterns.iter().map(Regex::as_str))?;
+
+    info!("Patterns:");
+    for (n, re) in patterns.iter().enumerate() {
+        println!("{}", pattern_color(n).paint(&format!("{}: {re}", n + 1)));
+    }
+
+    let mut matched_inputs = 0;
+    for input in inputs {
+        let name = input.name();
+        let haystack = match input.read() {
+            Ok(haystack) => haystack,
+            Err(err) if inputs.len() > 1 => {
+                warn!("{err}");
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        let matching_patterns = set.matches(&haystack);
+        if !matching_patterns.matched_any() {
+            info!("No pattern matches {name}");
+            continue;
+        }
+        matched_inputs += 1;
+
+        // (pattern index, span) of every match, of every pattern that matches at all.
+        let mut spans: Vec<(usize, Range<usize>)> = Vec::new();
+        for n in matching_patterns.iter() {
+            spans.extend(patterns[n].find_iter(&haystack).map(|m| (n, m.range())));
+        }
+        let overlaps = overlaps(&spans);
+
+        let mut highlights: Vec<Highlight> = spans
+            .iter()
+            .map(|(n, span)| Highlight {
+                span: span.clone(),
+                color: pattern_color(*n),
+                label: Some((n + 1).to_string()),
+            })
+            .collect();
+        // Overlaps are never longer than the spans they're part of, so they win when rendering.
+        highlights.extend(overlaps.iter().map(|(_, span)| Highlight {
+            span: span.clone(),
+            color: OVERLAP_COLOR,
+            label: Some("overlap".to_owned()),
+        }));
+
+        info!("Overlay of {name}:");
+        let overlay = highlight::render(&haystack, 0..haystack.len(), &highlights);
+        println!("{}", overlay.strip_suffix('\n').unwrap_or(&overlay));
+
+        info!("Matches per pattern in {name}:");
+        for n in 0..patterns.len() {
+            let count = spans.iter().filter(|(p, _)| *p == n).count();
+            println!("{}", pattern_color(n).paint(&format!("{}: {count}", n + 1)));
+        }
+
+        if !overlaps.is_empty() {
+            info!("Overlapping matches in {name}:");
+            for ((a, b), span) in &overlaps {
+                let (line, col) = matching::line_col(&haystack, span.start);
+                println!(
+                    "{} {line}:{col}: patterns {} and {} both match {:?}",
+                    OVERLAP_COLOR.paint("overlap"),
+                    a + 1,
+                    b + 1,
+                    highlight::display_bytes(&haystack[span.clone()])
+                );
+            }
+        }
+    }
+
+    if matched_inputs == 0 {
+        return Err(ProgError::NoMatch);
+    }
+    Ok(())
+}
+
+fn pattern_color(n: usize) -> PaletteColor {
+    palette::nth(n)
+}
+
+/// Intersections of matches of different patterns, with the pair of patterns involved.
+fn overlaps(spans: &[(usize, Range<usize>)]) -> Vec<((usize, usize), Range<usize>)> {
+    let mut sorted: Vec<&(usize, Range<usize>)> = spans.iter().collect();
+    sorted.sort_by_key(|(_, span)| (span.start, span.end));
+
+    let mut overlaps = Vec::new();
+    for (i, (a, first)) in sorted.iter().enumerate() {
+        for (b, second) in sorted[i + 1..].iter() {
+            if second.start >= first.end {
+                break;
+            }
+            if a != b {
+                let span = second.start..first.end.min(second.end);
+                overlaps.push(((*a.min(b), *a.max(b)), span));
+            }
+        }
+    }
+    overlaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn three_way_overlap_gives_every_pair() {
+        let spans = [(0, 0..10), (1, 2..6), (2, 4..8)];
+        assert_eq!(
+            overlaps(&spans),
+            [((0, 1), 2..6), ((0, 2), 4..8), ((1, 2), 4..6)]
+        );
+    }
+
+    #[test]
+    fn long_span_overlaps_every_short_one_it_covers() {
+        let spans = [(1, 2..4), (0, 0..20), (1, 6..8), (1, 18..25)];
+        assert_eq!(
+            overlaps(&spans),
+            [((0, 1), 2..4), ((0, 1), 6..8), ((0, 1), 18..20)]
+        );
+    }
+
+    #[test]
+    fn same_pattern_and_touching_spans_dont_overlap() {
+        let spans = [(0, 0..4), (0, 2..6), (1, 6..8)];
+        assert_eq!(overlaps(&spans), []);
+    }
+}
@@ -0,0 +1,216 @@
+use colored::{Color, Colorize};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Colors capture groups are picked from, unless `--palette` is given.
+const DEFAULT_PALETTE: [PaletteColor; 6] = [
+    PaletteColor::Named(Color::Blue),
+    PaletteColor::Named(Color::Green),
+    PaletteColor::Named(Color::Red),
+    PaletteColor::Named(Color::Cyan),
+    PaletteColor::Named(Color::Yellow),
+    PaletteColor::Named(Color::BrightBlue),
+];
+
+static PALETTE: OnceLock<Vec<PaletteColor>> = OnceLock::new();
+
+/// A color as given on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteColor {
+    /// One of the 16 basic terminal colors, e.g. `blue` or `bright-red`.
+    Named(Color),
+    /// An index into the 256-color palette, e.g. `208`.
+    Ansi256(u8),
+    /// A 24-bit color, e.g. `#ff8700`.
+    Rgb(u8, u8, u8),
+}
+
+impl From<Color> for PaletteColor {
+    fn from(color: Color) -> Self {
+        PaletteColor::Named(color)
+    }
+}
+
+impl FromStr for PaletteColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix('#') {
+            if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!("{s:?} isn't a #rrggbb color"));
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).expect("hex digits");
+            return Ok(PaletteColor::Rgb(channel(0), channel(2), channel(4)));
+        }
+        if s.bytes().all(|b| b.is_ascii_digit()) {
+            return s
+                .parse()
+                .map(PaletteColor::Ansi256)
+                .map_err(|_| format!("{s} is out of range, 256-color indexes go up to 255"));
+        }
+        s.replace(['-', '_'], " ")
+            .parse()
+            .map(PaletteColor::Named)
+            .map_err(|()| {
+                format!(
+                    "unknown color {s:?}, expected a name (e.g. blue, bright-red), 0-255 or #rrggbb"
+                )
+            })
+    }
+}
+
+impl PaletteColor {
+    /// `text` in this color, unless colors are disabled.
+    pub fn paint(self, text: &str) -> String {
+        match self {
+            PaletteColor::Named(color) => text.color(color).to_string(),
+            PaletteColor::Rgb(r, g, b) => text.truecolor(r, g, b).to_string(),
+            PaletteColor::Ansi256(index) => text.color(Color::AnsiColor(index)).to_string(),
+        }
+    }
+
+    /// CSS equivalent, using the xterm palette for named colors and 256-color indexes.
+    pub fn css(self) -> String {
+        let (r, g, b) = self.rgb();
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+
+    fn rgb(self) -> (u8, u8, u8) {
+        const BASIC: [(u8, u8, u8); 16] = [
+            (0x00, 0x00, 0x00),
+            (0xcd, 0x00, 0x00),
+            (0x00, 0xcd, 0x00),
+            (0xcd, 0xcd, 0x00),
+            (0x00, 0x00, 0xee),
+            (0xcd, 0x00, 0xcd),
+            (0x00, 0xcd, 0xcd),
+            (0xe5, 0xe5, 0xe5),
+            (0x7f, 0x7f, 0x7f),
+            (0xff, 0x00, 0x00),
+            (0x00, 0xff, 0x00),
+            (0xff, 0xff, 0x00),
+            (0x5c, 0x5c, 0xff),
+            (0xff, 0x00, 0xff),
+            (0x00, 0xff, 0xff),
+            (0xff, 0xff, 0xff),
+        ];
+        const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+        match self {
+            PaletteColor::Named(Color::TrueColor { r, g, b }) | PaletteColor::Rgb(r, g, b) => {
+                (r, g, b)
+            }
+            PaletteColor::Named(Color::AnsiColor(index)) => PaletteColor::Ansi256(index).rgb(),
+            PaletteColor::Named(color) => BASIC[basic_index(color)],
+            PaletteColor::Ansi256(index @ 0..16) => BASIC[index as usize],
+            PaletteColor::Ansi256(index @ 16..232) => {
+                let index = (index - 16) as usize;
+                (CUBE[index / 36], CUBE[index / 6 % 6], CUBE[index % 6])
+            }
+            PaletteColor::Ansi256(index) => {
+                let gray = 8 + 10 * (index - 232);
+                (gray, gray, gray)
+            }
+        }
+    }
+}
+
+/// Index of a basic color in the 256-color palette.
+fn basic_index(color: Color) -> usize {
+    match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::White => 7,
+        Color::BrightBlack => 8,
+        Color::BrightRed => 9,
+        Color::BrightGreen => 10,
+        Color::BrightYellow => 11,
+        Color::BrightBlue => 12,
+        Color::BrightMagenta => 13,
+        Color::BrightCyan => 14,
+        Color::BrightWhite | Color::TrueColor { .. } | Color::AnsiColor(_) => 15,
+    }
+}
+
+/// Replaces the default palette. Has no effect when `colors` is empty.
+pub fn init(colors: Vec<PaletteColor>) {
+    if !colors.is_empty() {
+        PALETTE
+            .set(colors)
+            .expect("palette should only be set once");
+    }
+}
+
+fn palette() -> &'static [PaletteColor] {
+    PALETTE.get().map_or(&DEFAULT_PALETTE, Vec::as_slice)
+}
+
+/// The `n`th color of the palette, wrapping around.
+pub fn nth(n: usize) -> PaletteColor {
+    let palette = palette();
+    palette[n % palette.len()]
+}
+
+/// Colors of the capture groups of a pattern, picked so that groups get distinct colors
+/// as long as the palette has enough of them.
+pub struct GroupColors(Vec<PaletteColor>);
+
+impl GroupColors {
+    /// Colors for groups 1, 2, ... with the given names (`None` for positional groups).
+    ///
+    /// Named groups prefer the color picked by a hash of their name, so that e.g.
+    /// `currentValue` is usually the same color in every pattern and every run, and move on
+    /// to the next free color when another group already has it. Positional groups get the
+    /// colors that are left, in order.
+    pub fn new<'a>(names: impl IntoIterator<Item = Option<&'a str>>) -> Self {
+        let palette = palette();
+        let names: Vec<Option<&str>> = names.into_iter().collect();
+        let mut used = vec![false; palette.len()];
+        let mut take = |preferred: usize| {
+            let free = (0..palette.len())
+                .map(|i| (preferred + i) % palette.len())
+                .find(|&slot| !used[slot]);
+            let slot = free.unwrap_or_else(|| {
+                // More groups than colors: start over
+                used.fill(false);
+                preferred % palette.len()
+            });
+            used[slot] = true;
+            slot
+        };
+
+        let mut slots = vec![0; names.len()];
+        for (i, name) in names.iter().enumerate() {
+            if let Some(name) = name {
+                slots[i] = take((fnv1a(name) % palette.len() as u64) as usize);
+            }
+        }
+        for (i, name) in names.iter().enumerate() {
+            if name.is_none() {
+                slots[i] = take(i);
+            }
+        }
+        GroupColors(slots.into_iter().map(|slot| palette[slot]).collect())
+    }
+
+    /// Color of the group with capture index `index`. The whole match (0) gets the first color.
+    pub fn get(&self, index: usize) -> PaletteColor {
+        index
+            .checked_sub(1)
+            .and_then(|i| self.0.get(i))
+            .copied()
+            .unwrap_or_else(|| nth(0))
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the std hasher is the same on every run and platform.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
@@ -0,0 +1,213 @@
+use super::{ConfigSource, Event, relative};
+use crate::{RegexFlags, diagnose, matching};
+
+/// The kinds of problems a renovate scan can find in custom managers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    NoFiles,
+    NoMatch,
+    ManagerWithoutMatch,
+    MissingFields,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 4] = [
+        Rule::NoFiles,
+        Rule::NoMatch,
+        Rule::ManagerWithoutMatch,
+        Rule::MissingFields,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::NoFiles => "no-matching-files",
+            Rule::NoMatch => "match-string-without-match",
+            Rule::ManagerWithoutMatch => "manager-without-match",
+            Rule::MissingFields => "missing-capture-group",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Rule::NoFiles => "The managerFilePatterns of a custom manager match no files.",
+            Rule::NoMatch => {
+                "A matchString matches nothing in a file matched by the managerFilePatterns."
+            }
+            Rule::ManagerWithoutMatch => {
+                "None of the matchStrings of a custom manager match any of its files."
+            }
+            Rule::MissingFields => {
+                "A match lacks a field Renovate requires (currentValue or currentDigest, \
+                 depName and datasource), and no template provides it."
+            }
+        }
+    }
+
+    /// Whether the problem breaks the manager, rather than possibly being intended.
+    pub fn is_error(&self) -> bool {
+        match self {
+            Rule::NoFiles | Rule::ManagerWithoutMatch | Rule::MissingFields => true,
+            Rule::NoMatch => false,
+        }
+    }
+}
+
+/// A position in a scanned file.
+pub struct FileLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+pub struct Finding {
+    pub rule: Rule,
+    pub manager: usize,
+    pub message: String,
+    /// Where in a scanned file the problem is, if it's about a file.
+    pub location: Option<FileLocation>,
+    /// Line in the renovate config of the manager or matchString concerned.
+    pub config_line: usize,
+}
+
+/// Collects findings from the events of a scan.
+pub struct Findings {
+    pub config: ConfigSource,
+    pub findings: Vec<Finding>,
+}
+
+impl Findings {
+    pub fn new(config: ConfigSource) -> Self {
+        Findings {
+            config,
+            findings: Vec::new(),
+        }
+    }
+
+    /// Returns the findings the event led to, which are also kept in `findings`.
+    pub fn event(&mut self, event: &Event) -> &[Finding] {
+        let before = self.findings.len();
+        match *event {
+            Event::NoMatch {
+                manager,
+                matcher,
+                file,
+                match_string,
+                re,
+                haystack,
+            } => {
+                let miss = diagnose::near_miss(re.as_str(), &RegexFlags::default(), haystack).ok();
+                let (line, column) = miss
+                    .as_ref()
+                    .and_then(|miss| miss.found.as_ref())
+                    .map_or((1, 1), |found| matching::line_col(haystack, found.end));
+                let mut message = format!(
+                    "matchStrings[{match_string}] of custom manager {manager} matches nothing in {}",
+                    relative(file)
+                );
+                if let Some(miss) = &miss {
+                    message = format!("{message}: {}", miss.summary(re.as_str()));
+                }
+                self.findings.push(Finding {
+                    rule: Rule::NoMatch,
+                    manager,
+                    message,
+                    location: Some(FileLocation {
+                        file: relative(file),
+                        line,
+                        column,
+                    }),
+                    config_line: self
+                        .config
+                        .match_string_line(manager, matcher, match_string),
+                });
+            }
+            Event::Matched {
+                manager,
+                matcher,
+                file,
+                match_string,
+                haystack,
+                matches,
+                ..
+            } => {
+                for m in matches {
+                    let missing = matcher.missing_fields(m);
+                    if missing.is_empty() {
+                        continue;
+                    }
+                    let (line, column) = matching::line_col(haystack, m.span.start);
+                    self.findings.push(Finding {
+                        rule: Rule::MissingFields,
+                        manager,
+                        message: format!(
+                            "Match of matchStrings[{match_string}] of custom manager {manager} \
+                             has no {} (neither a capture group nor a template)",
+                            missing.join(", ")
+                        ),
+                        location: Some(FileLocation {
+                            file: relative(file),
+                            line,
+                            column,
+                        }),
+                        config_line: self
+                            .config
+                            .match_string_line(manager, matcher, match_string),
+                    });
+                }
+            }
+            Event::FileFinished {
+                manager,
+                matcher,
+                file,
+                missing,
+            } if !missing.is_empty() => self.findings.push(Finding {
+                rule: Rule::MissingFields,
+                manager,
+                message: format!(
+                    "The matches of custom manager {manager} in {} have no {} between them \
+                     (neither a capture group nor a template), as matchStringsStrategy {} \
+                     requires",
+                    relative(file),
+                    missing.join(", "),
+                    matcher.strategy.as_str()
+                ),
+                location: Some(FileLocation {
+                    file: relative(file),
+                    line: 1,
+                    column: 1,
+                }),
+                config_line: self.config.manager_line(manager, matcher),
+            }),
+            Event::ManagerFinished {
+                manager,
+                matcher,
+                files: 0,
+                ..
+            } => self.findings.push(Finding {
+                rule: Rule::NoFiles,
+                manager,
+                message: format!("managerFilePatterns of custom manager {manager} match no files"),
+                location: None,
+                config_line: self.config.manager_line(manager, matcher),
+            }),
+            Event::ManagerFinished {
+                manager,
+                matcher,
+                files,
+                matches: 0,
+            } => self.findings.push(Finding {
+                rule: Rule::ManagerWithoutMatch,
+                manager,
+                message: format!(
+                    "Custom manager {manager} found no matches in the {files} {} matched by its \
+                     managerFilePatterns",
+                    if files == 1 { "file" } else { "files" }
+                ),
+                location: None,
+                config_line: self.config.manager_line(manager, matcher),
+            }),
+            _ => (),
+        }
+        &self.findings[before..]
+    }
+}
@@ -0,0 +1,56 @@
+use super::findings::Findings;
+use super::{ConfigSource, Event, Reporter};
+
+/// Prints a GitHub Actions workflow command for every finding, which the runner turns
+/// into an annotation on the file and line.
+pub struct GithubReporter {
+    findings: Findings,
+}
+
+impl GithubReporter {
+    pub fn new(config: ConfigSource) -> Self {
+        GithubReporter {
+            findings: Findings::new(config),
+        }
+    }
+}
+
+impl Reporter for GithubReporter {
+    fn event(&mut self, event: Event) {
+        let config_uri = self.findings.config.uri();
+        for finding in self.findings.event(&event) {
+            let (file, line, column) = match &finding.location {
+                Some(location) => (location.file.clone(), location.line, location.column),
+                None => (config_uri.clone(), finding.config_line, 1),
+            };
+            println!(
+                "::{} file={},line={line},col={column},title={}::{}",
+                if finding.rule.is_error() {
+                    "error"
+                } else {
+                    "warning"
+                },
+                escape_property(&file),
+                escape_property(finding.rule.id()),
+                escape_data(&finding.message)
+            );
+        }
+    }
+}
+
+/// Whether we're running in a GitHub Actions workflow, where annotations are picked up.
+pub fn in_github_actions() -> bool {
+    std::env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true")
+}
+
+/// Escapes the message of a workflow command.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a `key=value` property of a workflow command.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
@@ -0,0 +1,295 @@
+use super::findings::Findings;
+use super::{ConfigSource, Event, Reporter, relative};
+use crate::highlight::{self, Highlight};
+use crate::match_parts;
+use crate::matching::{self, CapState, Match};
+use crate::palette::{GroupColors, PaletteColor};
+use regex::bytes::Regex;
+use std::fmt::Write;
+
+/// Files with more lines than this start collapsed.
+const OPEN_LINES: usize = 50;
+
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+pre { background: #f6f8fa; padding: .75em; overflow-x: auto; }
+mark { background: #fff3b0; }
+.group { font-weight: bold; }
+.legend span { margin-right: 1em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: .2em .5em; text-align: left; }
+.error { color: #b00020; }
+.warning { color: #8a6d00; }
+.absent { color: #888; font-style: italic; }";
+
+/// Collects every scanned file with its matches highlighted and prints a single HTML
+/// document, without external assets, once the scan is done.
+pub struct HtmlReporter {
+    findings: Findings,
+    managers: Vec<ManagerReport>,
+}
+
+struct ManagerReport {
+    index: usize,
+    file_patterns: Vec<String>,
+    match_strings: Vec<MatchStringReport>,
+    /// Findings, as list items.
+    problems: Vec<String>,
+}
+
+struct MatchStringReport {
+    /// The pattern with its groups colored, and a legend of them.
+    pattern: String,
+    legend: String,
+    /// A section per scanned file.
+    files: Vec<String>,
+}
+
+impl HtmlReporter {
+    pub fn new(config: ConfigSource) -> Self {
+        HtmlReporter {
+            findings: Findings::new(config),
+            managers: Vec::new(),
+        }
+    }
+}
+
+impl Reporter for HtmlReporter {
+    fn event(&mut self, event: Event) {
+        if let Event::ManagerStarted { manager, matcher } = event {
+            self.managers.push(ManagerReport {
+                index: manager,
+                file_patterns: matcher.file_patterns.clone(),
+                match_strings: matcher.regexes.iter().map(|p| pattern_view(p)).collect(),
+                problems: Vec::new(),
+            });
+        }
+        let report = self
+            .managers
+            .last_mut()
+            .expect("manager should have started");
+
+        let found = self.findings.event(&event);
+        for finding in found {
+            let mut problem = String::new();
+            if let Some(location) = &finding.location {
+                write!(
+                    problem,
+                    "<code>{}:{}:{}</code> ",
+                    escape(&location.file),
+                    location.line,
+                    location.column
+                )
+                .unwrap();
+            }
+            problem.push_str(&escape(&finding.message));
+            report.problems.push(format!(
+                "<li class=\"{}\">{problem}</li>",
+                if finding.rule.is_error() {
+                    "error"
+                } else {
+                    "warning"
+                }
+            ));
+        }
+
+        match event {
+            Event::Matched {
+                file,
+                match_string,
+                re,
+                haystack,
+                matches,
+                ..
+            } => {
+                let summary = format!(
+                    "{} {}",
+                    matches.len(),
+                    if matches.len() == 1 {
+                        "match"
+                    } else {
+                        "matches"
+                    }
+                );
+                let mut section = file_view(&relative(file), &summary, haystack, matches);
+                section.push_str(&captures_table(re, haystack, matches));
+                section.push_str("</details>\n");
+                report.match_strings[match_string].files.push(section);
+            }
+            Event::NoMatch {
+                file,
+                match_string,
+                haystack,
+                ..
+            } => {
+                let mut section = file_view(&relative(file), "no match", haystack, &[]);
+                for finding in found {
+                    writeln!(
+                        section,
+                        "<p class=\"warning\">{}</p>",
+                        escape(&finding.message)
+                    )
+                    .unwrap();
+                }
+                section.push_str("</details>\n");
+                report.match_strings[match_string].files.push(section);
+            }
+            _ => (),
+        }
+    }
+
+    fn finish(&mut self) {
+        let config = escape(&self.findings.config.uri());
+        let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+        html.push_str("<meta charset=\"utf-8\">\n");
+        writeln!(html, "<title>{config}: Renovate custom managers</title>").unwrap();
+        writeln!(html, "<style>\n{STYLE}\n</style>\n</head>\n<body>").unwrap();
+        writeln!(
+            html,
+            "<h1>Renovate custom managers in <code>{config}</code></h1>"
+        )
+        .unwrap();
+
+        for report in &self.managers {
+            writeln!(
+                html,
+                "<h2 id=\"manager-{0}\">customManagers[{0}]</h2>",
+                report.index
+            )
+            .unwrap();
+            let file_patterns: Vec<String> = report
+                .file_patterns
+                .iter()
+                .map(|pattern| format!("<code>{}</code>", escape(pattern)))
+                .collect();
+            writeln!(
+                html,
+                "<p>managerFilePatterns: {}</p>",
+                file_patterns.join(" ")
+            )
+            .unwrap();
+            if !report.problems.is_empty() {
+                writeln!(html, "<ul>\n{}\n</ul>", report.problems.join("\n")).unwrap();
+            }
+
+            for (n, match_string) in report.match_strings.iter().enumerate() {
+                writeln!(html, "<h3>matchStrings[{n}]</h3>").unwrap();
+                writeln!(html, "<pre>{}</pre>", match_string.pattern).unwrap();
+                if !match_string.legend.is_empty() {
+                    writeln!(html, "<p class=\"legend\">{}</p>", match_string.legend).unwrap();
+                }
+                for file in &match_string.files {
+                    html.push_str(file);
+                }
+            }
+        }
+        html.push_str("</body>\n</html>");
+        println!("{html}");
+    }
+}
+
+/// The pattern with every group colored, and a legend of the groups.
+fn pattern_view(pattern: &str) -> MatchStringReport {
+    // Patterns that don't compile were rejected before the scan started
+    let re = Regex::new(pattern).ok();
+    let colors = GroupColors::new(re.iter().flat_map(|re| re.capture_names().skip(1)));
+    let highlights: Vec<Highlight> = highlight::pattern_groups(pattern, false)
+        .into_iter()
+        .map(|group| Highlight {
+            span: group.span,
+            color: colors.get(group.index),
+            label: None,
+        })
+        .collect();
+    let legend: Vec<String> = re
+        .iter()
+        .flat_map(|re| re.capture_names().enumerate().skip(1))
+        .map(|(i, name)| paint(&matching::group_label(i, name), Some(colors.get(i))))
+        .collect();
+    MatchStringReport {
+        pattern: highlight::render_with(pattern.as_bytes(), 0..pattern.len(), &highlights, paint),
+        legend: legend.join("\n"),
+        files: Vec::new(),
+    }
+}
+
+/// Opens a section with the whole file, marking every match and coloring its groups.
+fn file_view(file: &str, summary: &str, haystack: &[u8], matches: &[Match]) -> String {
+    let mut text = String::new();
+    let mut pos = 0;
+    for m in matches {
+        text.push_str(&escape(&highlight::display_bytes(
+            &haystack[pos..m.span.start],
+        )));
+        let (highlights, _) = match_parts(m);
+        write!(
+            text,
+            "<mark>{}</mark>",
+            highlight::render_with(haystack, m.span.clone(), &highlights, paint)
+        )
+        .unwrap();
+        pos = m.span.end;
+    }
+    text.push_str(&escape(&highlight::display_bytes(&haystack[pos..])));
+
+    let lines = haystack.iter().filter(|&&b| b == b'\n').count() + 1;
+    format!(
+        "<details{}>\n<summary><code>{}</code>: {summary}</summary>\n<pre>{text}</pre>\n",
+        if lines > OPEN_LINES { "" } else { " open" },
+        escape(file)
+    )
+}
+
+/// A row per match, with the line it starts on and the value of every group.
+fn captures_table(re: &Regex, haystack: &[u8], matches: &[Match]) -> String {
+    if re.captures_len() == 1 {
+        return String::new();
+    }
+    let colors = GroupColors::new(re.capture_names().skip(1));
+    let mut table = String::from("<table>\n<tr><th>Match</th><th>Line</th>");
+    for (i, name) in re.capture_names().enumerate().skip(1) {
+        write!(
+            table,
+            "<th>{}</th>",
+            paint(&matching::group_label(i, name), Some(colors.get(i)))
+        )
+        .unwrap();
+    }
+    table.push_str("</tr>\n");
+    for (n, m) in matches.iter().enumerate() {
+        let (line, _) = matching::line_col(haystack, m.span.start);
+        write!(table, "<tr><td>{}</td><td>{line}</td>", n + 1).unwrap();
+        for cap in &m.caps {
+            let value = match cap.state {
+                CapState::Matched => paint(&cap.value, Some(colors.get(cap.index))),
+                CapState::Empty => "<span class=\"absent\">empty</span>".to_owned(),
+                CapState::NotParticipating => {
+                    "<span class=\"absent\">did not participate</span>".to_owned()
+                }
+            };
+            write!(table, "<td>{value}</td>").unwrap();
+        }
+        table.push_str("</tr>\n");
+    }
+    table.push_str("</table>\n");
+    table
+}
+
+fn paint(text: &str, color: Option<PaletteColor>) -> String {
+    match color {
+        Some(color) => format!(
+            "<span class=\"group\" style=\"color: {}\">{}</span>",
+            color.css(),
+            escape(text)
+        ),
+        None => escape(text),
+    }
+}
+
+/// Escapes text for use in HTML elements and attributes.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,192 @@
+use super::findings::{Finding, Findings};
+use super::{ConfigSource, Event, MatchStringsStrategy, Reporter, relative};
+use std::fmt::Write;
+
+/// Collects a test suite per custom manager, with a test case per scanned file and
+/// matchString, and prints them as JUnit XML once the scan is done.
+pub struct JunitReporter {
+    findings: Findings,
+    suites: Vec<Suite>,
+}
+
+struct Suite {
+    name: String,
+    cases: Vec<Case>,
+}
+
+struct Case {
+    name: String,
+    failure: Option<Failure>,
+}
+
+struct Failure {
+    message: String,
+    details: String,
+}
+
+impl Failure {
+    /// Fails with the first finding as message and all of them, with their locations,
+    /// as details.
+    fn new(findings: &[Finding], pattern: Option<&str>) -> Self {
+        let mut details = String::new();
+        if let Some(pattern) = pattern {
+            writeln!(details, "Pattern: {pattern}").unwrap();
+        }
+        for finding in findings {
+            if let Some(location) = &finding.location {
+                write!(
+                    details,
+                    "{}:{}:{}: ",
+                    location.file, location.line, location.column
+                )
+                .unwrap();
+            }
+            writeln!(details, "{}", finding.message).unwrap();
+        }
+        Failure {
+            message: findings[0].message.clone(),
+            details,
+        }
+    }
+}
+
+impl JunitReporter {
+    pub fn new(config: ConfigSource) -> Self {
+        JunitReporter {
+            findings: Findings::new(config),
+            suites: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, name: String, failure: Option<Failure>) {
+        let suite = self.suites.last_mut().expect("manager should have started");
+        suite.cases.push(Case { name, failure });
+    }
+}
+
+impl Reporter for JunitReporter {
+    fn event(&mut self, event: Event) {
+        let found = self.findings.event(&event);
+        match event {
+            Event::ManagerStarted { manager, matcher } => self.suites.push(Suite {
+                name: format!(
+                    "customManagers[{manager}] ({})",
+                    matcher.file_patterns.join(", ")
+                ),
+                cases: Vec::new(),
+            }),
+            Event::NoMatch {
+                file,
+                match_string,
+                re,
+                ..
+            } => {
+                let failure = Failure::new(found, Some(re.as_str()));
+                self.push(
+                    format!("{} matchStrings[{match_string}]", relative(file)),
+                    Some(failure),
+                );
+            }
+            Event::Matched {
+                file,
+                match_string,
+                re,
+                ..
+            } => {
+                // Every incomplete match fails the scan, so it fails the case too.
+                let failure = (!found.is_empty()).then(|| Failure::new(found, Some(re.as_str())));
+                self.push(
+                    format!("{} matchStrings[{match_string}]", relative(file)),
+                    failure,
+                );
+            }
+            Event::FileFinished { matcher, file, .. }
+                if matcher.strategy != MatchStringsStrategy::Any =>
+            {
+                let failure = (!found.is_empty()).then(|| Failure::new(found, None));
+                self.push(
+                    format!(
+                        "{} matchStringsStrategy {}",
+                        relative(file),
+                        matcher.strategy.as_str()
+                    ),
+                    failure,
+                );
+            }
+            Event::ManagerFinished { files: 0, .. } => {
+                let failure = Failure::new(found, None);
+                self.push("managerFilePatterns".to_owned(), Some(failure));
+            }
+            Event::FileMatched { .. }
+            | Event::FileFinished { .. }
+            | Event::ManagerFinished { .. } => (),
+        }
+    }
+
+    fn finish(&mut self) {
+        let failures = |cases: &[Case]| cases.iter().filter(|c| c.failure.is_some()).count();
+        let tests: usize = self.suites.iter().map(|s| s.cases.len()).sum();
+        let failed: usize = self.suites.iter().map(|s| failures(&s.cases)).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        writeln!(
+            xml,
+            "<testsuites name=\"{}\" tests=\"{tests}\" failures=\"{failed}\">",
+            escape(&self.findings.config.uri())
+        )
+        .unwrap();
+        for suite in &self.suites {
+            writeln!(
+                xml,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">",
+                escape(&suite.name),
+                suite.cases.len(),
+                failures(&suite.cases)
+            )
+            .unwrap();
+            for case in &suite.cases {
+                write!(
+                    xml,
+                    "    <testcase name=\"{}\" classname=\"{}\"",
+                    escape(&case.name),
+                    escape(&suite.name)
+                )
+                .unwrap();
+                match &case.failure {
+                    None => xml.push_str("/>\n"),
+                    Some(failure) => {
+                        writeln!(
+                            xml,
+                            ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                            escape(&failure.message),
+                            escape(&failure.details)
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>");
+        println!("{xml}");
+    }
+}
+
+/// Escapes text for use in XML attributes and elements.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Other control characters aren't allowed in XML 1.0 at all
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,214 @@
+use super::findings::Findings;
+use super::{ConfigSource, Event, Reporter, relative};
+use crate::matching;
+use std::fmt::Write;
+
+/// Sections with more rows than this start collapsed.
+const OPEN_ROWS: usize = 10;
+
+/// Collects what every custom manager found and prints it as a Markdown report, e.g. for
+/// a pull request comment, once the scan is done.
+pub struct MarkdownReporter {
+    findings: Findings,
+    managers: Vec<ManagerReport>,
+}
+
+#[derive(Default)]
+struct ManagerReport {
+    index: usize,
+    file_patterns: Vec<String>,
+    files: Vec<String>,
+    dependencies: Vec<Dependency>,
+    /// Formatted findings, in the order they were found.
+    problems: Vec<String>,
+    errors: usize,
+}
+
+/// The fields Renovate extracts from a single match.
+struct Dependency {
+    file: String,
+    line: usize,
+    match_string: usize,
+    dep_name: Option<String>,
+    current_value: Option<String>,
+    datasource: Option<String>,
+}
+
+impl MarkdownReporter {
+    pub fn new(config: ConfigSource) -> Self {
+        MarkdownReporter {
+            findings: Findings::new(config),
+            managers: Vec::new(),
+        }
+    }
+}
+
+impl Reporter for MarkdownReporter {
+    fn event(&mut self, event: Event) {
+        if let Event::ManagerStarted { manager, matcher } = event {
+            self.managers.push(ManagerReport {
+                index: manager,
+                file_patterns: matcher.file_patterns.clone(),
+                ..Default::default()
+            });
+        }
+        let report = self
+            .managers
+            .last_mut()
+            .expect("manager should have started");
+
+        for finding in self.findings.event(&event) {
+            let mut problem = String::from(if finding.rule.is_error() {
+                "**error**"
+            } else {
+                "**warning**"
+            });
+            if let Some(location) = &finding.location {
+                write!(
+                    problem,
+                    " `{}:{}:{}`",
+                    location.file, location.line, location.column
+                )
+                .unwrap();
+            }
+            write!(problem, " {}", finding.message).unwrap();
+            report.problems.push(problem);
+            report.errors += usize::from(finding.rule.is_error());
+        }
+
+        match event {
+            Event::FileMatched { file, .. } => report.files.push(relative(file)),
+            Event::Matched {
+                matcher,
+                file,
+                match_string,
+                haystack,
+                matches,
+                ..
+            } => {
+                for m in matches {
+                    report.dependencies.push(Dependency {
+                        file: relative(file),
+                        line: matching::line_col(haystack, m.span.start).0,
+                        match_string,
+                        dep_name: matcher.field(m, "depName"),
+                        current_value: matcher.field(m, "currentValue"),
+                        datasource: matcher.field(m, "datasource"),
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn finish(&mut self) {
+        let mut md = String::from("## Renovate custom managers\n\n");
+        writeln!(md, "Scanned with `{}`.\n", self.findings.config.uri()).unwrap();
+        md.push_str("| Manager | managerFilePatterns | Files | Matches | Problems |\n");
+        md.push_str("|---|---|--:|--:|---|\n");
+        for report in &self.managers {
+            let warnings = report.problems.len() - report.errors;
+            let problems = match (report.errors, warnings) {
+                (0, 0) => "none".to_owned(),
+                (0, warnings) => count(warnings, "warning"),
+                (errors, 0) => format!("**{}**", count(errors, "error")),
+                (errors, warnings) => {
+                    format!(
+                        "**{}**, {}",
+                        count(errors, "error"),
+                        count(warnings, "warning")
+                    )
+                }
+            };
+            writeln!(
+                md,
+                "| [{index}](#custommanagers{index}) | {} | {} | {} | {problems} |",
+                report
+                    .file_patterns
+                    .iter()
+                    .map(|pattern| code(pattern))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                report.files.len(),
+                report.dependencies.len(),
+                index = report.index,
+            )
+            .unwrap();
+        }
+
+        for report in &self.managers {
+            writeln!(md, "\n### customManagers[{}]\n", report.index).unwrap();
+
+            let problems: String = report
+                .problems
+                .iter()
+                .map(|problem| format!("- {}\n", cell(problem)))
+                .collect();
+            details(&mut md, "Problems", report.problems.len(), &problems);
+
+            let mut rows = String::from(
+                "| File | Line | matchString | depName | currentValue | datasource |\n\
+                 |---|--:|--:|---|---|---|\n",
+            );
+            for dependency in &report.dependencies {
+                let field =
+                    |value: &Option<String>| value.as_deref().map_or("_missing_".to_owned(), code);
+                writeln!(
+                    rows,
+                    "| {} | {} | {} | {} | {} | {} |",
+                    code(&dependency.file),
+                    dependency.line,
+                    dependency.match_string,
+                    field(&dependency.dep_name),
+                    field(&dependency.current_value),
+                    field(&dependency.datasource)
+                )
+                .unwrap();
+            }
+            details(&mut md, "Matches", report.dependencies.len(), &rows);
+
+            let files: String = report
+                .files
+                .iter()
+                .map(|file| format!("- {}\n", code(file)))
+                .collect();
+            details(&mut md, "Files scanned", report.files.len(), &files);
+        }
+        print!("{md}");
+    }
+}
+
+/// Appends a `<details>` section, which starts collapsed when it's long.
+fn details(md: &mut String, summary: &str, rows: usize, body: &str) {
+    if rows == 0 {
+        writeln!(md, "{summary}: none\n").unwrap();
+        return;
+    }
+    writeln!(
+        md,
+        "<details{}><summary>{summary} ({rows})</summary>\n\n{body}\n</details>\n",
+        if rows > OPEN_ROWS { "" } else { " open" }
+    )
+    .unwrap();
+}
+
+fn count(n: usize, noun: &str) -> String {
+    format!("{n} {noun}{}", if n == 1 { "" } else { "s" })
+}
+
+/// Formats `text` as inline code that's safe to use in a table cell.
+fn code(text: &str) -> String {
+    let text = cell(text);
+    // A code span needs a longer run of backticks than it contains
+    let fence = "`".repeat(text.split(|c| c != '`').map(str::len).max().unwrap_or(0) + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{fence} {text} {fence}")
+    } else {
+        format!("{fence}{text}{fence}")
+    }
+}
+
+/// Keeps `text` on one line and from ending a table cell.
+fn cell(text: &str) -> String {
+    text.replace('\n', " ").replace('|', "\\|")
+}
@@ -0,0 +1,640 @@
+use crate::input::Input;
+use crate::matching::{self, CapState, Match};
+use crate::{MatchOptions, ProgError, RegexFlags, print_count, print_matches, print_pattern};
+use clap::ValueEnum;
+use log::{debug, warn};
+use regex::bytes::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+mod findings;
+mod github;
+mod html;
+mod junit;
+mod markdown;
+mod ndjson;
+mod sarif;
+
+#[derive(Debug, Deserialize)]
+pub struct CustomMatcher {
+    #[serde(rename = "customType")]
+    type_: String,
+    #[serde(rename = "managerFilePatterns")]
+    pub file_patterns: Vec<String>,
+    #[serde(rename = "matchStrings")]
+    pub regexes: Vec<String>,
+    #[serde(rename = "currentValueTemplate")]
+    current_value_template: Option<String>,
+    #[serde(rename = "depNameTemplate")]
+    dep_name_template: Option<String>,
+    #[serde(rename = "packageNameTemplate")]
+    package_name_template: Option<String>,
+    #[serde(rename = "datasourceTemplate")]
+    datasource_template: Option<String>,
+    #[serde(rename = "matchStringsStrategy", default)]
+    pub strategy: MatchStringsStrategy,
+}
+
+/// How Renovate combines the matches of several matchStrings into dependencies.
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchStringsStrategy {
+    /// Every match of every matchString is a dependency of its own.
+    #[default]
+    Any,
+    /// Every matchString runs on the matches of the one before it.
+    Recursive,
+    /// The matches of all matchStrings in a file make up a single dependency.
+    Combination,
+}
+
+impl MatchStringsStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchStringsStrategy::Any => "any",
+            MatchStringsStrategy::Recursive => "recursive",
+            MatchStringsStrategy::Combination => "combination",
+        }
+    }
+}
+
+impl CustomMatcher {
+    /// Fields Renovate requires for every dependency that `m` doesn't capture
+    /// and that no template of the manager provides either.
+    ///
+    /// Always empty unless the `matchStringsStrategy` is `any`: otherwise a dependency is made
+    /// up of several matches, see `missing_combined_fields`.
+    pub fn missing_fields(&self, m: &Match) -> Vec<&'static str> {
+        if self.strategy != MatchStringsStrategy::Any {
+            return Vec::new();
+        }
+        self.missing(|name| captured(m, name))
+    }
+
+    /// Like `missing_fields`, but for the `recursive` and `combination` strategies, where
+    /// the fields are spread over the matches of several matchStrings. Checks what all
+    /// `matches` in a file captured together. Always empty for the `any` strategy.
+    pub fn missing_combined_fields(&self, matches: &[Match]) -> Vec<&'static str> {
+        if self.strategy == MatchStringsStrategy::Any {
+            return Vec::new();
+        }
+        self.missing(|name| matches.iter().any(|m| captured(m, name)))
+    }
+
+    fn missing(&self, captured: impl Fn(&str) -> bool) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        // A digest can stand in for the version
+        if !captured("currentValue")
+            && !captured("currentDigest")
+            && self.current_value_template.is_none()
+        {
+            missing.push("currentValue");
+        }
+        if !captured("depName")
+            && !captured("packageName")
+            && self.dep_name_template.is_none()
+            && self.package_name_template.is_none()
+        {
+            missing.push("depName");
+        }
+        if !captured("datasource") && self.datasource_template.is_none() {
+            missing.push("datasource");
+        }
+        missing
+    }
+
+    /// Value of the field `name` for the match `m`: what its group of that name captured,
+    /// or else the manager's template for it. `depName` falls back to `packageName`.
+    pub fn field(&self, m: &Match, name: &str) -> Option<String> {
+        let captured = |name: &str| {
+            m.caps
+                .iter()
+                .find(|cap| cap.name.as_deref() == Some(name) && cap.state == CapState::Matched)
+                .map(|cap| cap.value.clone())
+        };
+        let template = match name {
+            "currentValue" => &self.current_value_template,
+            "depName" => &self.dep_name_template,
+            "packageName" => &self.package_name_template,
+            "datasource" => &self.datasource_template,
+            _ => &None,
+        };
+        let value = captured(name).or_else(|| template.clone());
+        if name == "depName" {
+            value.or_else(|| self.field(m, "packageName"))
+        } else {
+            value
+        }
+    }
+}
+
+/// Whether a group named `name` captured something in `m`.
+fn captured(m: &Match, name: &str) -> bool {
+    m.caps
+        .iter()
+        .any(|cap| cap.name.as_deref() == Some(name) && cap.state == CapState::Matched)
+}
+
+#[derive(Debug, Deserialize)]
+struct RenovateScheme {
+    #[serde(rename = "customManagers")]
+    custom_matchers: Vec<CustomMatcher>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored, human readable output
+    #[default]
+    Text,
+    /// Newline-delimited JSON events, printed as the scan goes
+    Ndjson,
+    /// A SARIF 2.1.0 log of problems with the custom managers
+    Sarif,
+    /// JUnit XML, with a test suite per custom manager
+    Junit,
+    /// GitHub Actions workflow commands, which annotate the files in pull requests
+    Github,
+    /// A Markdown report per custom manager, e.g. for pull request comments
+    Markdown,
+    /// A self-contained HTML page with the matches highlighted in every scanned file
+    Html,
+}
+
+/// The renovate config file, kept around to point findings at the right line.
+pub struct ConfigSource {
+    pub path: PathBuf,
+    pub text: String,
+    /// Byte range of every object in `customManagers`, in order.
+    managers: Vec<Range<usize>>,
+}
+
+impl ConfigSource {
+    pub fn new(path: PathBuf, text: String) -> Self {
+        let managers = manager_spans(&text);
+        ConfigSource {
+            path,
+            text,
+            managers,
+        }
+    }
+
+    /// Line of the custom manager `manager`, preferring the line of its first file pattern.
+    pub fn manager_line(&self, manager: usize, matcher: &CustomMatcher) -> usize {
+        let Some(span) = self.managers.get(manager) else {
+            return 1;
+        };
+        let offset = matcher
+            .file_patterns
+            .first()
+            .and_then(|pattern| self.find_in(span, pattern, 0))
+            .unwrap_or(span.start);
+        matching::line_col(self.text.as_bytes(), offset).0
+    }
+
+    /// Line of `matchStrings[match_string]` of the custom manager `manager`, or of the
+    /// manager if it can't be found (e.g. because it's written with different escapes).
+    pub fn match_string_line(
+        &self,
+        manager: usize,
+        matcher: &CustomMatcher,
+        match_string: usize,
+    ) -> usize {
+        let regex = &matcher.regexes[match_string];
+        // The same pattern may be in the list more than once
+        let earlier = matcher.regexes[..match_string]
+            .iter()
+            .filter(|other| *other == regex)
+            .count();
+        self.managers
+            .get(manager)
+            .and_then(|span| self.find_in(span, regex, earlier))
+            .map_or_else(
+                || self.manager_line(manager, matcher),
+                |offset| matching::line_col(self.text.as_bytes(), offset).0,
+            )
+    }
+
+    /// Offset of the occurrence after the `skip` first ones of `value` as a JSON string
+    /// within `span`.
+    fn find_in(&self, span: &Range<usize>, value: &str, skip: usize) -> Option<usize> {
+        let needle = crate::escape::json_string(value);
+        self.text[span.clone()]
+            .match_indices(&needle)
+            .nth(skip)
+            .map(|(offset, _)| span.start + offset)
+    }
+
+    /// Path of the config relative to the repository root.
+    pub fn uri(&self) -> String {
+        relative(&self.path)
+    }
+}
+
+/// Something that happened while scanning the files of a custom manager.
+///
+/// `manager` is the index of the manager in `customManagers`, and `match_string` the index of
+/// the pattern in its `matchStrings`.
+#[derive(Clone, Copy)]
+pub enum Event<'a> {
+    ManagerStarted {
+        manager: usize,
+        matcher: &'a CustomMatcher,
+    },
+    FileMatched {
+        manager: usize,
+        file: &'a Path,
+        file_pattern: &'a str,
+    },
+    Matched {
+        manager: usize,
+        matcher: &'a CustomMatcher,
+        file: &'a Path,
+        match_string: usize,
+        re: &'a Regex,
+        haystack: &'a [u8],
+        matches: &'a [Match],
+    },
+    /// With `matchStringsStrategy` `recursive`, `match_string` ran on the matches of the one
+    /// before it, and the ones after it don't run on the file.
+    NoMatch {
+        manager: usize,
+        matcher: &'a CustomMatcher,
+        file: &'a Path,
+        match_string: usize,
+        re: &'a Regex,
+        haystack: &'a [u8],
+    },
+    /// All matchStrings ran on the file. `missing` lists the fields Renovate requires that
+    /// the matches of the file don't capture together, for managers whose
+    /// `matchStringsStrategy` makes a dependency out of several matches.
+    FileFinished {
+        manager: usize,
+        matcher: &'a CustomMatcher,
+        file: &'a Path,
+        missing: &'a [&'static str],
+    },
+    ManagerFinished {
+        manager: usize,
+        matcher: &'a CustomMatcher,
+        files: usize,
+        matches: usize,
+    },
+}
+
+/// Turns scan events into output.
+pub trait Reporter {
+    fn event(&mut self, event: Event);
+
+    /// Called once the scan is done.
+    fn finish(&mut self) {}
+}
+
+/// Reports to both reporters, in order.
+impl<A: Reporter, B: Reporter> Reporter for (A, B) {
+    fn event(&mut self, event: Event) {
+        self.0.event(event);
+        self.1.event(event);
+    }
+
+    fn finish(&mut self) {
+        self.0.finish();
+        self.1.finish();
+    }
+}
+
+/// Finds all regex customManagers in the renovate config `file` and runs their
+/// matchStrings on every file matched by their managerFilePatterns.
+pub fn run(file: &Path, output: OutputFormat) -> Result<(), ProgError> {
+    let renovate_config_file = match fs::read_to_string(file) {
+        Ok(data) => data,
+        Err(err) => return Err(ProgError::IO(format!("failed to read {file:?}"), err)),
+    };
+    let renovate_config: RenovateScheme = match serde_json::from_str(&renovate_config_file) {
+        Ok(conf) => conf,
+        Err(err) => return Err(ProgError::Config(format!("failed to parse {file:?}"), err)),
+    };
+
+    let config = ConfigSource::new(file.to_owned(), renovate_config_file);
+    let mut reporter: Box<dyn Reporter> = match output {
+        // Annotate the pull request as well when run in a workflow
+        OutputFormat::Text if github::in_github_actions() => {
+            Box::new((TextReporter, github::GithubReporter::new(config)))
+        }
+        OutputFormat::Text => Box::new(TextReporter),
+        OutputFormat::Ndjson => Box::new(ndjson::NdjsonReporter),
+        OutputFormat::Sarif => Box::new(sarif::SarifReporter::new(config)),
+        OutputFormat::Junit => Box::new(junit::JunitReporter::new(config)),
+        OutputFormat::Github => Box::new(github::GithubReporter::new(config)),
+        OutputFormat::Markdown => Box::new(markdown::MarkdownReporter::new(config)),
+        OutputFormat::Html => Box::new(html::HtmlReporter::new(config)),
+    };
+    let failing_managers = scan(&renovate_config, reporter.as_mut())?;
+    reporter.finish();
+
+    if failing_managers > 0 {
+        return Err(ProgError::Validation(format!(
+            "{failing_managers} custom {} found no matches or missed required capture groups",
+            if failing_managers == 1 {
+                "manager"
+            } else {
+                "managers"
+            }
+        )));
+    }
+    Ok(())
+}
+
+/// Runs every regex custom manager, returning how many of them found no matches
+/// or had matches missing fields Renovate requires.
+fn scan(config: &RenovateScheme, reporter: &mut dyn Reporter) -> Result<usize, ProgError> {
+    let mut failing_managers = 0;
+
+    for (index, matcher) in config.custom_matchers.iter().enumerate() {
+        if matcher.type_.to_lowercase() != "regex" {
+            continue;
+        }
+
+        let mut regexes = Vec::new();
+        for regex in &matcher.regexes {
+            debug!("Parsing regex: {regex}");
+            match Regex::new(regex) {
+                Ok(re) => regexes.push(re),
+                Err(err) => {
+                    return Err(ProgError::ParseFailure(
+                        format!("failed to parse matchString {regex:?}"),
+                        err.into(),
+                    ));
+                }
+            }
+        }
+
+        reporter.event(Event::ManagerStarted {
+            manager: index,
+            matcher,
+        });
+        let (mut files, mut matches) = (0, 0);
+        let mut missing_fields = false;
+
+        for (file, file_pattern) in &matched_files(matcher)? {
+            let file = file.as_path();
+            files += 1;
+            reporter.event(Event::FileMatched {
+                manager: index,
+                file,
+                file_pattern,
+            });
+
+            let haystack = match Input::File(file.to_owned()).read() {
+                Ok(haystack) => haystack,
+                Err(err) => {
+                    warn!("{err}");
+                    continue;
+                }
+            };
+
+            let mut file_matches = Vec::new();
+            // Parts of the file the next matchString runs on
+            let whole_file = 0..haystack.len();
+            let mut scopes = vec![whole_file];
+            for (match_string, re) in regexes.iter().enumerate() {
+                debug!("Running regex: {re}");
+                let found: Vec<Match> = scopes
+                    .iter()
+                    .flat_map(|scope| {
+                        matching::find_matches(re, &haystack[scope.clone()])
+                            .into_iter()
+                            .map(|m| m.offset(scope.start))
+                    })
+                    .collect();
+                if found.is_empty() {
+                    debug!("Found no match for {re} in {file:?}");
+                    reporter.event(Event::NoMatch {
+                        manager: index,
+                        matcher,
+                        file,
+                        match_string,
+                        re,
+                        haystack: &haystack,
+                    });
+                    if matcher.strategy == MatchStringsStrategy::Recursive {
+                        // The matchStrings after it have nothing to run on
+                        break;
+                    }
+                    continue;
+                }
+                matches += found.len();
+                missing_fields |= found.iter().any(|m| !matcher.missing_fields(m).is_empty());
+                reporter.event(Event::Matched {
+                    manager: index,
+                    matcher,
+                    file,
+                    match_string,
+                    re,
+                    haystack: &haystack,
+                    matches: &found,
+                });
+                if matcher.strategy == MatchStringsStrategy::Recursive {
+                    scopes = found.iter().map(|m| m.span.clone()).collect();
+                }
+                file_matches.extend(found);
+            }
+
+            let missing = matcher.missing_combined_fields(&file_matches);
+            missing_fields |= !missing.is_empty();
+            reporter.event(Event::FileFinished {
+                manager: index,
+                matcher,
+                file,
+                missing: &missing,
+            });
+        }
+
+        reporter.event(Event::ManagerFinished {
+            manager: index,
+            matcher,
+            files,
+            matches,
+        });
+        if matches == 0 || missing_fields {
+            failing_managers += 1;
+        }
+    }
+
+    Ok(failing_managers)
+}
+
+/// Byte ranges of the objects in the top-level `customManagers` array of a renovate config.
+///
+/// A lightweight scan of the JSON structure: the config has already been parsed, so it's
+/// known to be valid.
+fn manager_spans(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let (mut depth, mut in_managers, mut start) = (0, false, 0);
+    // The last string seen directly in the top-level object, i.e. the key of what follows
+    let mut key = "";
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                let begin = i + 1;
+                i = begin;
+                while bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                if depth == 1 {
+                    key = &text[begin..i];
+                }
+            }
+            b'[' | b'{' => {
+                if depth == 1 && bytes[i] == b'[' && key == "customManagers" {
+                    in_managers = true;
+                } else if in_managers && depth == 2 {
+                    start = i;
+                }
+                depth += 1;
+            }
+            b']' | b'}' => {
+                depth -= 1;
+                if in_managers && depth == 2 {
+                    spans.push(start..i + 1);
+                } else if in_managers && depth == 1 {
+                    break;
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    spans
+}
+
+/// Every file matched by any of the managerFilePatterns of `matcher`, along with the first
+/// pattern that matched it. Files matched by several patterns are only listed once.
+fn matched_files(matcher: &CustomMatcher) -> Result<Vec<(PathBuf, &str)>, ProgError> {
+    let mut file_regexes = Vec::new();
+    for file_pattern in &matcher.file_patterns {
+        debug!("File pattern: {file_pattern}");
+        // Removing leading and trailing slashes (/)
+        let pattern = file_pattern.trim_matches('/');
+        debug!("File pattern trimmed: {pattern}");
+
+        let file_regex = match regex::Regex::new(pattern) {
+            Ok(re) => re,
+            Err(err) => {
+                return Err(ProgError::ParseFailure(
+                    format!("failed to parse file pattern {pattern:?}"),
+                    err.into(),
+                ));
+            }
+        };
+
+        debug!("File pattern parsed: {file_regex}");
+        file_regexes.push((file_pattern.as_str(), file_regex));
+    }
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(".") {
+        let entry = match entry {
+            Ok(path) => path,
+            Err(_) => continue,
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        // Easier to work with filename
+        let path = entry.path().to_str().expect("dir entry should exist");
+        debug!("Walking into {path:?}");
+
+        let found = file_regexes.iter().find(|(_, file_regex)| {
+            let mut file = path;
+            if !file_regex.to_string().starts_with(".") {
+                // Remove leading ./ from file name as it might clash with regex.
+                file = file.trim_start_matches("./");
+            }
+            file_regex.is_match(file)
+        });
+        let Some((file_pattern, file_regex)) = found else {
+            debug!("Skipping dir cuz no regex mach");
+            continue;
+        };
+        debug!("Found a match with {file_regex:?} on {path:?}");
+        files.push((entry.into_path(), *file_pattern));
+    }
+    Ok(files)
+}
+
+/// Path of a scanned file relative to the repository root, without the leading `./`.
+fn relative(file: &Path) -> String {
+    let file = file.strip_prefix(".").unwrap_or(file);
+    file.display().to_string()
+}
+
+/// The default colored output, with the same match display as the default program.
+struct TextReporter;
+
+impl Reporter for TextReporter {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Matched {
+                matcher,
+                file,
+                re,
+                haystack,
+                matches,
+                ..
+            } => {
+                let input = Input::File(file.to_owned());
+                let options = MatchOptions::default();
+                print_pattern(re, &RegexFlags::default(), &options);
+                print_matches(&input, haystack, matches, &options);
+                print_count(matches.len(), &input.name());
+                for (n, m) in matches.iter().enumerate() {
+                    let missing = matcher.missing_fields(m);
+                    if !missing.is_empty() {
+                        warn!("Match {} is missing {}", n + 1, missing.join(", "));
+                    }
+                }
+            }
+            Event::FileFinished { file, missing, .. } if !missing.is_empty() => warn!(
+                "The matches in {} are missing {}",
+                relative(file),
+                missing.join(", ")
+            ),
+            Event::ManagerFinished {
+                manager,
+                matches: 0,
+                ..
+            } => warn!("Custom manager {manager} found no matches"),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manager_spans_skip_nested_and_quoted_brackets() {
+        let text = r#"{
+  "extends": [{"customManagers": [{}]}],
+  "description": "customManagers",
+  "customManagers": [
+    {"matchStrings": ["[{\"}"], "x": {"y": []}},
+    {}
+  ],
+  "after": [{}]
+}"#;
+        let spans = manager_spans(text);
+        let managers: Vec<&str> = spans.into_iter().map(|span| &text[span]).collect();
+        assert_eq!(
+            managers,
+            [r#"{"matchStrings": ["[{\"}"], "x": {"y": []}}"#, "{}"]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use super::{Event, Reporter, relative};
+use crate::json::JsonMatch;
+use serde::Serialize;
+
+/// Prints every event as a line of JSON as soon as it happens.
+pub struct NdjsonReporter;
+
+/// One line of `--output ndjson`, tagged by its `event` field.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Line<'a> {
+    ManagerStarted {
+        manager: usize,
+        file_patterns: &'a [String],
+        match_strings: &'a [String],
+    },
+    FileMatched {
+        manager: usize,
+        file: String,
+        file_pattern: &'a str,
+    },
+    Match {
+        manager: usize,
+        file: String,
+        match_string: usize,
+        pattern: &'a str,
+        #[serde(rename = "match")]
+        found: JsonMatch,
+    },
+    NoMatch {
+        manager: usize,
+        file: String,
+        match_string: usize,
+        pattern: &'a str,
+    },
+    FileFinished {
+        manager: usize,
+        file: String,
+        missing: &'a [&'static str],
+    },
+    ManagerFinished {
+        manager: usize,
+        files: usize,
+        matches: usize,
+    },
+}
+
+impl NdjsonReporter {
+    fn print(line: &Line) {
+        println!(
+            "{}",
+            serde_json::to_string(line).expect("event should serialize")
+        );
+    }
+}
+
+impl Reporter for NdjsonReporter {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::ManagerStarted { manager, matcher } => Self::print(&Line::ManagerStarted {
+                manager,
+                file_patterns: &matcher.file_patterns,
+                match_strings: &matcher.regexes,
+            }),
+            Event::FileMatched {
+                manager,
+                file,
+                file_pattern,
+            } => Self::print(&Line::FileMatched {
+                manager,
+                file: relative(file),
+                file_pattern,
+            }),
+            Event::Matched {
+                manager,
+                file,
+                match_string,
+                re,
+                haystack,
+                matches,
+                ..
+            } => {
+                for m in matches {
+                    Self::print(&Line::Match {
+                        manager,
+                        file: relative(file),
+                        match_string,
+                        pattern: re.as_str(),
+                        found: JsonMatch::new(haystack, m),
+                    });
+                }
+            }
+            Event::NoMatch {
+                manager,
+                file,
+                match_string,
+                re,
+                ..
+            } => Self::print(&Line::NoMatch {
+                manager,
+                file: relative(file),
+                match_string,
+                pattern: re.as_str(),
+            }),
+            Event::FileFinished {
+                manager,
+                file,
+                missing,
+                ..
+            } => Self::print(&Line::FileFinished {
+                manager,
+                file: relative(file),
+                missing,
+            }),
+            Event::ManagerFinished {
+                manager,
+                files,
+                matches,
+                ..
+            } => Self::print(&Line::ManagerFinished {
+                manager,
+                files,
+                matches,
+            }),
+        }
+    }
+}
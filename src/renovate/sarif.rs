@@ -0,0 +1,98 @@
+use super::findings::{Findings, Rule};
+use super::{ConfigSource, Event, Reporter};
+use serde_json::{Value, json};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Collects findings and prints them as a SARIF 2.1.0 log once the scan is done.
+pub struct SarifReporter {
+    findings: Findings,
+}
+
+impl SarifReporter {
+    pub fn new(config: ConfigSource) -> Self {
+        SarifReporter {
+            findings: Findings::new(config),
+        }
+    }
+}
+
+impl Reporter for SarifReporter {
+    fn event(&mut self, event: Event) {
+        self.findings.event(&event);
+    }
+
+    fn finish(&mut self) {
+        let config_uri = self.findings.config.uri();
+        let location = |uri: &str, line: usize, column: usize| {
+            json!({
+                "physicalLocation": {
+                    "artifactLocation": { "uri": uri },
+                    "region": { "startLine": line, "startColumn": column }
+                }
+            })
+        };
+
+        let rules: Vec<Value> = Rule::ALL
+            .iter()
+            .map(|rule| {
+                json!({
+                    "id": rule.id(),
+                    "shortDescription": { "text": rule.description() },
+                    "defaultConfiguration": { "level": level(*rule) }
+                })
+            })
+            .collect();
+
+        let results: Vec<Value> = self
+            .findings
+            .findings
+            .iter()
+            .map(|finding| {
+                let config_location = location(&config_uri, finding.config_line, 1);
+                let mut result = json!({
+                    "ruleId": finding.rule.id(),
+                    "ruleIndex": Rule::ALL.iter().position(|r| *r == finding.rule),
+                    "level": level(finding.rule),
+                    "message": { "text": finding.message },
+                });
+                match &finding.location {
+                    Some(file) => {
+                        let mut related = config_location;
+                        related["id"] = json!(0);
+                        related["message"] =
+                            json!({ "text": format!("custom manager {}", finding.manager) });
+                        result["locations"] = json!([location(&file.file, file.line, file.column)]);
+                        result["relatedLocations"] = json!([related]);
+                    }
+                    None => result["locations"] = json!([config_location]),
+                }
+                result
+            })
+            .collect();
+
+        let log = json!({
+            "$schema": SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": "https://github.com/sklirg/regex404",
+                        "rules": rules
+                    }
+                },
+                "results": results
+            }]
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&log).expect("SARIF log should serialize")
+        );
+    }
+}
+
+fn level(rule: Rule) -> &'static str {
+    if rule.is_error() { "error" } else { "warning" }
+}